@@ -1,2 +1,45 @@
 app-name = COSMIC Launcher
 type-to-search = Type to search apps or type “?” for more options...
+backend-unavailable = Search backend unavailable
+retry = Retry
+launch-failed = Could not launch application
+launch-on-gpu = Launch on { $gpu }
+remember-gpu = Always use this GPU for the app
+launch-on-workspace = Launch on workspace { $number }
+launch-on-new-workspace = Launch on a new workspace
+launch-on-output = Launch on { $output }
+category-audio-video = Sound & Video
+category-development = Development
+category-education = Education
+category-game = Games
+category-graphics = Graphics
+category-network = Internet
+category-office = Office
+category-science = Science
+category-settings = Settings
+category-system = System
+category-utility = Accessories
+category-other = Other
+search-library = Search apps
+result-position = { $position } of { $total }
+mode-apps = Applications
+mode-calc = Calculator
+mode-files = Files
+mode-find = Find files
+mode-recent = Recent files
+mode-terminal = Terminal
+mode-help = Help
+mode-open-with = Open with { $app }
+footer-position = { $mode } · { $position } of { $total }
+footer-no-results = { $mode } · No results
+hint-launch = Enter: launch
+hint-select = Enter: select
+hint-open = Enter: open
+hint-complete = Tab: complete
+hint-actions = Shift+F10: actions
+no-results = No results for “{ $query }”
+fallback-web = Search the web for “{ $query }”
+fallback-shell = Run “{ $query }”
+fallback-terminal = Run “{ $query }” in a terminal
+fallback-files = Search files for “{ $query }”
+fallback-store = Search the COSMIC Store for “{ $query }”
@@ -1,10 +1,26 @@
 use crate::app::iced::event::listen_raw;
-use crate::subscriptions::launcher;
-use crate::{components, fl};
-use clap::Parser;
+use crate::config::Config;
+use crate::dmenu::{self, Dmenu};
+use crate::fallbacks::{self, Fallback};
+use crate::icons::{self, Icons};
+use crate::library;
+use crate::matching::fuzzy_score;
+use crate::open_with::{self, Completion};
+use crate::overlap::{self, Edges};
+use crate::sizing::{self, Sizing};
+use crate::subscriptions::gpus::{self, Gpu};
+use crate::subscriptions::providers::{self, Providers};
+use crate::subscriptions::scripts::{self, Scripts};
+use crate::subscriptions::wayland::{self, Placement};
+use crate::subscriptions::{dbus, launcher};
+use crate::{components, fl, localize, terminal};
+use clap::{Parser, ValueEnum};
 use cosmic::app::{Core, CosmicFlags, Settings, Task};
 use cosmic::cctk::sctk;
+use cosmic::cctk::sctk::reexports::protocols::xdg::shell::client::xdg_positioner;
 use cosmic::cctk::sctk::shell::wlr_layer;
+use cosmic::config::CosmicTk;
+use cosmic::cosmic_config;
 use cosmic::dbus_activation::Details;
 use cosmic::iced::alignment::{Horizontal, Vertical};
 use cosmic::iced::core::text::{Ellipsize, EllipsizeHeightLimit};
@@ -39,22 +55,24 @@ use cosmic::iced::{
 };
 use cosmic::surface::action::{LiveSettings, app_layer_shell, simple_layer_shell};
 use cosmic::theme::{self, Button, Container};
-use cosmic::widget::icon::IconFallback;
 use cosmic::widget::space::{horizontal as horizontal_space, vertical as vertical_space};
 use cosmic::widget::text_input::{self, StyleSheet as TextInputStyleSheet};
 use cosmic::widget::{autosize, button, divider, icon, id_container, mouse_area, scrollable, text};
-use cosmic::{Element, keyboard_nav, surface};
+use cosmic::{Application, Element, keyboard_nav, surface};
 use iced::keyboard::{Key, Modifiers};
+use iced::mouse::ScrollDelta;
 use iced::{Alignment, Color};
 use pop_launcher::{ContextOption, GpuPreference, IconSource, SearchResult};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, VecDeque};
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fmt::Display;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 use std::rc::Rc;
 use std::str::FromStr;
 use std::sync::LazyLock;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use tracing::{debug, error, info};
 
@@ -65,6 +83,23 @@ static SCROLLABLE: LazyLock<Id> = LazyLock::new(|| Id::new("scrollable"));
 
 pub(crate) static MENU_ID: LazyLock<SurfaceId> = LazyLock::new(SurfaceId::unique);
 const SCROLL_MIN: usize = 8;
+/// Height of a result row and the divider below it.
+const ROW_HEIGHT: f32 = 49.;
+/// Height of the search input.
+const INPUT_HEIGHT: f32 = 32.;
+/// Space above the launcher, and between the input and the results.
+const SPACING: f32 = 16.;
+/// Padding around the launcher's content, vertically and horizontally.
+const PADDING: [f32; 2] = [24., 32.];
+/// Horizontal padding of a result row.
+const ROW_PADDING: f32 = 24.;
+/// Distance scrolled with a touchpad that moves alt-tab focus by one window.
+const WHEEL_STEP: f32 = 40.;
+/// Width of an app in the library grid.
+const TILE_WIDTH: f32 = 128.;
+/// Time the compositor has to hand out an activation token before an app is
+/// launched without one.
+const TOKEN_TIMEOUT: Duration = Duration::from_secs(1);
 
 #[derive(Parser, Debug, Serialize, Deserialize, Clone)]
 #[command(author, version, about, long_about = None)]
@@ -72,6 +107,9 @@ const SCROLL_MIN: usize = 8;
 pub struct Args {
     #[clap(subcommand)]
     pub subcommand: Option<LauncherTasks>,
+    #[clap(skip)]
+    #[serde(skip)]
+    pub dmenu: Option<Dmenu>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, clap::Subcommand)]
@@ -80,10 +118,92 @@ pub enum LauncherTasks {
     AltTab,
     #[clap(about = "Toggle the launcher and switch to the alt-tab view")]
     ShiftAltTab,
+    #[clap(about = "Open the launcher if closed")]
+    Show,
+    #[clap(about = "Open the launcher if closed, otherwise close it")]
+    Toggle,
     #[clap(about = "Start the launcher with an input")]
-    Input { input: Option<String> },
-    #[clap(about = "Close the launcher if open")]
+    Input {
+        input: Option<String>,
+        #[arg(long, value_enum, help = "Search with a pop-launcher plugin")]
+        #[serde(default)]
+        mode: Option<Mode>,
+        #[arg(
+            long,
+            requires = "input",
+            help = "Launch the first result without opening the launcher"
+        )]
+        #[serde(default)]
+        activate_first: bool,
+    },
+    #[clap(about = "Close the launcher if open", visible_alias = "hide")]
     Close,
+    #[clap(about = "Open the app library, or close it if open")]
+    Library,
+    #[clap(about = "Print the results for a query without opening the launcher")]
+    Query {
+        query: String,
+        #[arg(long, value_enum, default_value_t = crate::query::Format::Json)]
+        format: crate::query::Format,
+        #[arg(long, value_name = "N", help = "Activate the result at index N")]
+        activate: Option<usize>,
+    },
+    #[clap(about = "Pick one of the lines read from stdin and print it, like dmenu")]
+    Dmenu {
+        #[arg(short = 'p', long, help = "Text shown in the empty search input")]
+        prompt: Option<String>,
+    },
+}
+
+/// A pop-launcher plugin that `Input --mode` searches with.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, clap::ValueEnum)]
+pub enum Mode {
+    /// Evaluate math expressions
+    Calc,
+    /// Browse files by path
+    Files,
+    /// Search for files by name
+    Find,
+    /// Recently opened files
+    Recent,
+    /// Run a command in a terminal
+    Terminal,
+    /// List the available plugins
+    Help,
+}
+
+impl Mode {
+    /// The query prefix that pop-launcher dispatches to the plugin.
+    fn prefix(self) -> &'static str {
+        match self {
+            Self::Calc => "= ",
+            Self::Files => "~/",
+            Self::Find => "find ",
+            Self::Recent => "recent ",
+            Self::Terminal => "t:",
+            Self::Help => "?",
+        }
+    }
+
+    /// Returns the mode whose plugin a query is dispatched to, if any.
+    fn of(query: &str) -> Option<Self> {
+        Self::value_variants()
+            .iter()
+            .copied()
+            .find(|mode| query.starts_with(mode.prefix()))
+            .or_else(|| query.starts_with('/').then_some(Self::Files))
+    }
+
+    fn name(self) -> String {
+        match self {
+            Self::Calc => fl!("mode-calc"),
+            Self::Files => fl!("mode-files"),
+            Self::Find => fl!("mode-find"),
+            Self::Recent => fl!("mode-recent"),
+            Self::Terminal => fl!("mode-terminal"),
+            Self::Help => fl!("mode-help"),
+        }
+    }
 }
 
 impl Display for LauncherTasks {
@@ -109,19 +229,54 @@ impl CosmicFlags for Args {
     }
 }
 
-pub fn run() -> cosmic::iced::Result {
-    let args = Args::parse();
-    cosmic::app::run_single_instance::<CosmicLauncher>(
-        Settings::default()
-            .antialiasing(true)
-            .client_decorations(true)
-            .debug(false)
-            .default_text_size(16.0)
-            .scale_factor(1.0)
-            .no_main_window(true)
-            .exit_on_close(false),
-        args,
-    )
+pub fn run() -> Result<ExitCode, cosmic::iced::Error> {
+    let mut args = Args::parse();
+    match args.subcommand.take() {
+        Some(LauncherTasks::Query {
+            query,
+            format,
+            activate,
+        }) => Ok(crate::query::run(query, format, activate)),
+        // a separate instance, since the choices and the result are on this
+        // process' stdin and stdout
+        Some(LauncherTasks::Dmenu { prompt }) => {
+            let entries = match dmenu::read_entries(std::io::stdin().lock()) {
+                Ok(entries) => entries,
+                Err(why) => {
+                    eprintln!("cosmic-launcher: failed to read stdin: {why}");
+                    return Ok(ExitCode::FAILURE);
+                }
+            };
+            let dmenu = Dmenu::new(prompt, entries);
+            args.dmenu = Some(dmenu.clone());
+            cosmic::app::run::<CosmicLauncher>(settings(), args)?;
+
+            // closing without a choice is a cancellation, as in dmenu
+            Ok(match dmenu.take_choice() {
+                Some(choice) => {
+                    println!("{choice}");
+                    ExitCode::SUCCESS
+                }
+                None => ExitCode::FAILURE,
+            })
+        }
+        subcommand => {
+            args.subcommand = subcommand;
+            cosmic::app::run_single_instance::<CosmicLauncher>(settings(), args)?;
+            Ok(ExitCode::SUCCESS)
+        }
+    }
+}
+
+fn settings() -> Settings {
+    Settings::default()
+        .antialiasing(true)
+        .client_decorations(true)
+        .debug(false)
+        .default_text_size(16.0)
+        .scale_factor(1.0)
+        .no_main_window(true)
+        .exit_on_close(false)
 }
 
 pub fn menu_button<'a, Message: Clone + 'a>(
@@ -152,8 +307,15 @@ pub struct CosmicLauncher {
     input_value: String,
     surface_state: SurfaceState,
     launcher_items: Vec<SearchResult>,
-    launcher_item_icon_handles: Vec<Option<cosmic::widget::icon::Handle>>,
+    /// Icons of the results and library apps, resolved in the background.
+    icons: Icons,
     tx: Option<mpsc::Sender<launcher::Request>>,
+    service_state: launcher::ServiceState,
+    providers: Rc<RefCell<Providers>>,
+    scripts: Scripts,
+    script_search: Option<iced::task::Handle>,
+    /// Ids of results listed as sub-rows under the result before them.
+    sub_rows: HashSet<u32>,
     menu: Option<(u32, Vec<ContextOption>)>,
     cursor_position: Option<Point<f32>>,
     focused: usize,
@@ -163,12 +325,65 @@ pub struct CosmicLauncher {
     window_id: window::Id,
     queue: VecDeque<Message>,
     result_ids: Vec<Id>,
-    overlap: HashMap<String, Rectangle>,
-    margin: f32,
-    height: f32,
+    /// Layers overlapping the output, by identifier.
+    overlap: HashMap<String, overlap::Layer>,
+    /// Space taken from each edge of the output by avoided layers.
+    edges: Edges,
+    /// Logical size of the output the launcher opens on.
+    output_size: Size,
+    /// Whether `output_size` was measured by the dummy surface, which opens on
+    /// the same output as the launcher, rather than taken from output info.
+    /// Reset whenever the launcher is shown, as the active output may differ.
+    output_measured: bool,
+    sizing: Sizing,
     needs_clear: bool,
     hand_over: String,
     dummy_id: Option<window::Id>,
+    dbus: Option<dbus::Handle>,
+    dmenu: Option<Dmenu>,
+    config: Config,
+    config_handler: Option<cosmic_config::Config>,
+    gpus: Vec<Gpu>,
+    /// GPU chosen from the context menu for the result being activated.
+    pending_gpu: Option<usize>,
+    /// Whether to remember the GPU chosen from the context menu for the app.
+    remember_gpu: bool,
+    wayland: Option<wayland::Handle>,
+    layout: wayland::Layout,
+    /// Where to put the window of the result being activated.
+    pending_placement: Option<Placement>,
+    /// App to open files with, picked when the open-with separator is typed.
+    open_with: Option<SearchResult>,
+    /// Completions listed as results while typing files to open.
+    file_completions: Vec<Completion>,
+    /// Files to substitute into the `Exec` line of the app being activated.
+    pending_files: Vec<String>,
+    /// Activates the first result of the next update, for `Input --activate-first`.
+    activate_first: bool,
+    launch_error: Option<LaunchError>,
+    /// Touchpad scrolling not yet turned into alt-tab focus steps.
+    wheel_pixels: f32,
+    input_cursor: InputCursor,
+    /// Whether the app library is shown instead of the results list.
+    library: bool,
+    library_apps: Vec<library::App>,
+    /// Indices of the library apps matching the input, grouped by category.
+    library_groups: Vec<(library::Category, Vec<usize>)>,
+    /// Launches waiting for an activation token, by request.
+    launches: HashMap<u64, PendingLaunch>,
+    next_launch: u64,
+    /// Launches that went ahead without a token because the compositor did
+    /// not answer in time.
+    token_timeouts: u32,
+}
+
+/// An app to launch once an activation token is received.
+#[derive(Debug, Clone)]
+struct PendingLaunch {
+    app_id: String,
+    exec: String,
+    gpu_preference: GpuPreference,
+    terminal: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -184,15 +399,80 @@ pub enum Message {
     CursorMoved(Point<f32>),
     Hide,
     LauncherEvent(launcher::Event),
+    DbusEvent(dbus::Event),
     Layer(LayerEvent, window::Id),
     Output(OutputEvent),
     KeyboardNav(keyboard_nav::Action),
-    ActivationToken(Option<String>, String, String, GpuPreference, bool),
+    ActivationToken(u64, Option<String>),
+    TokenTimeout(u64),
     AltTab,
     ShiftAltTab,
     Opened(Size, window::Id),
     AltRelease,
     Overlap(OverlapNotifyEvent),
+    RetryService,
+    ScriptResults(String, Vec<scripts::Match>),
+    Gpus(Vec<Gpu>),
+    LaunchOnGpu(u32, usize),
+    RememberGpu,
+    ActivateOn(Placement),
+    LaunchOn(u32, Placement),
+    WaylandEvent(wayland::Event),
+    Config(Config),
+    ToggleLibrary,
+    LibraryLoaded(Vec<library::App>),
+    LibraryActivate(usize),
+    Navigate(Navigate),
+    Wheel(ScrollDelta),
+    /// Opens the context menu of the focused result, with Shift+F10 or the menu key.
+    KeyboardContext,
+    /// Activates a sub-row of the focused result, such as a desktop action,
+    /// with Ctrl+Shift+1 to Ctrl+Shift+9.
+    ActivateAction(usize),
+    /// Left or Right moved the input cursor.
+    InputCursorMoved,
+    /// Icons resolved for the requested keys, none if resolving failed.
+    IconsResolved(u64, Vec<icons::Key>, Vec<(icons::Key, icon::Handle)>),
+    IconTheme(String),
+}
+
+/// Focus movements beyond the next and previous result.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Navigate {
+    PageUp,
+    PageDown,
+    /// The first result, if `ctrl` is held or the input cursor cannot move.
+    Home {
+        ctrl: bool,
+    },
+    /// The last result, if `ctrl` is held or the input cursor cannot move.
+    End {
+        ctrl: bool,
+    },
+}
+
+/// Where the cursor of the search input is.
+///
+/// The input does not report its cursor, so it is followed from the keys
+/// that move it: typing keeps it at the end, Home and End move it to an edge,
+/// and Left and Right move it off one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputCursor {
+    Start,
+    Inside,
+    End,
+}
+
+impl InputCursor {
+    /// Follows the cursor through an edit that leaves `value`.
+    fn edited(self, value: &str) -> Self {
+        match self {
+            _ if value.is_empty() => Self::End,
+            // text typed at the start pushes the cursor off it
+            Self::Start => Self::Inside,
+            cursor => cursor,
+        }
+    }
 }
 
 impl CosmicLauncher {
@@ -207,10 +487,149 @@ impl CosmicLauncher {
         }
     }
 
+    /// Searches pop-launcher and the script sources triggered by `query`.
+    fn search(&mut self, query: String) -> Task<Message> {
+        self.request(launcher::Request::Search(query.clone()));
+
+        if let Some(handle) = self.script_search.take() {
+            handle.abort();
+        }
+        self.scripts.matches.clear();
+
+        let Some(search) = self.scripts.search(&query) else {
+            return Task::none();
+        };
+        let (task, handle) = Task::perform(search, move |matches| {
+            cosmic::action::app(Message::ScriptResults(query.clone(), matches))
+        })
+        .abortable();
+        self.script_search = Some(handle);
+        task
+    }
+
+    /// Builds the launcher without loading anything, so tests can supply the
+    /// config and providers.
+    fn new(
+        core: Core,
+        dmenu: Option<Dmenu>,
+        config: Config,
+        config_handler: Option<cosmic_config::Config>,
+        providers: Providers,
+    ) -> Self {
+        Self {
+            core,
+            input_value: String::new(),
+            surface_state: SurfaceState::Hidden,
+            launcher_items: Vec::new(),
+            icons: Icons::default(),
+            tx: None,
+            service_state: launcher::ServiceState::Starting,
+            providers: Rc::new(RefCell::new(providers)),
+            scripts: Scripts::default(),
+            script_search: None,
+            sub_rows: HashSet::new(),
+            menu: None,
+            cursor_position: None,
+            focused: 0,
+            last_hide: Instant::now(),
+            alt_tab: false,
+            alt_tab_released: false,
+            window_id: SurfaceId::unique(),
+            queue: VecDeque::new(),
+            result_ids: (0..10)
+                .map(|id| Id::new(id.to_string()))
+                .collect::<Vec<_>>(),
+            edges: Edges::default(),
+            overlap: HashMap::new(),
+            // until an output is reported
+            output_size: Size::new(1280., 800.),
+            output_measured: false,
+            sizing: Sizing::default(),
+            needs_clear: false,
+            hand_over: String::default(),
+            dummy_id: None,
+            dbus: None,
+            dmenu,
+            config,
+            config_handler,
+            gpus: Vec::new(),
+            pending_gpu: None,
+            remember_gpu: false,
+            wayland: None,
+            layout: wayland::Layout::default(),
+            pending_placement: None,
+            open_with: None,
+            file_completions: Vec::new(),
+            pending_files: Vec::new(),
+            activate_first: false,
+            launch_error: None,
+            wheel_pixels: 0.,
+            input_cursor: InputCursor::End,
+            library: false,
+            library_apps: Vec::new(),
+            library_groups: Vec::new(),
+            launches: HashMap::new(),
+            next_launch: 0,
+            token_timeouts: 0,
+        }
+    }
+
+    /// Sends a request for a result to whichever source produced it.
+    ///
+    /// Provider results are answered in-process, and their response is handled
+    /// as if it came from pop-launcher. Script results are activated by
+    /// running their script.
+    fn dispatch(&mut self, r: launcher::Request) -> Task<Message> {
+        let id = match r {
+            launcher::Request::Activate(id)
+            | launcher::Request::Context(id)
+            | launcher::Request::Complete(id)
+            | launcher::Request::ActivateContext(id, _) => Some(id),
+            _ => None,
+        };
+
+        if let Some(id) = id.filter(|id| fallbacks::owns(*id)) {
+            return match (r, fallbacks::get(&self.config.fallbacks, id)) {
+                (launcher::Request::Activate(_), Some(fallback)) => self.run_fallback(fallback),
+                _ => Task::none(),
+            };
+        }
+
+        if let Some(id) = id.filter(|id| scripts::owns(*id)) {
+            return match (r, self.scripts.get(id)) {
+                (launcher::Request::Activate(_), Some(m)) => {
+                    let m = m.clone();
+                    Task::batch([Task::future(scripts::activate(m)).discard(), self.hide()])
+                }
+                _ => Task::none(),
+            };
+        }
+
+        if !id.is_some_and(providers::owns) {
+            self.request(r);
+            return Task::none();
+        }
+
+        debug!("provider request: {:?}", r);
+        let response = self.providers.borrow_mut().handle(&r);
+        match response {
+            Some(response) => {
+                self.update(Message::LauncherEvent(launcher::Event::Response(response)))
+            }
+            None => Task::none(),
+        }
+    }
+
+    /// Opens a surface that reports the layers overlapping the output.
+    ///
+    /// Until the output is measured, it covers the output to measure it.
+    /// Afterwards it takes a single pixel in the top left corner, so overlaps
+    /// are still reported in output coordinates.
     fn create_dummy_layer_surface(&mut self) -> Task<Message> {
         self.needs_clear = true;
         let id = window::Id::unique();
         self.dummy_id = Some(id);
+        let measure = !self.output_measured;
         Task::batch(vec![
             cosmic::surface::surface_task(simple_layer_shell::<Message>(
                 || LiveSettings {
@@ -224,12 +643,16 @@ impl CosmicLauncher {
                         layer: wlr_layer::Layer::Bottom,
                         keyboard_interactivity: wlr_layer::KeyboardInteractivity::None,
                         input_zone: Some(Vec::new()),
-                        anchor: wlr_layer::Anchor::TOP,
+                        anchor: if measure {
+                            wlr_layer::Anchor::all()
+                        } else {
+                            wlr_layer::Anchor::TOP | wlr_layer::Anchor::LEFT
+                        },
                         output:
                             cosmic::iced::runtime::platform_specific::wayland::layer_surface::IcedOutput::Active,
                         namespace: "cosmic_launcher_dummy".into(),
                         margin: IcedMargin::default(),
-                        size: Some((Some(200), Some(200))),
+                        size: Some(if measure { (None, None) } else { (Some(1), Some(1)) }),
                         exclusive_zone: -1,
                         size_limits: Limits::NONE,
                     }
@@ -241,9 +664,390 @@ impl CosmicLauncher {
         ])
     }
 
+    /// Replaces the dummy surface with one measuring the active output.
+    fn measure_output(&mut self) -> Task<Message> {
+        self.output_measured = false;
+        let destroy = self
+            .dummy_id
+            .take()
+            .map_or_else(Task::none, destroy_layer_surface);
+        Task::batch([destroy, self.create_dummy_layer_surface()])
+    }
+
+    fn set_launcher_items(&mut self, list: Vec<SearchResult>) {
+        self.launcher_items.splice(.., list);
+        if self.result_ids.len() < self.launcher_items.len() {
+            self.result_ids.extend(
+                (self.result_ids.len()..self.launcher_items.len())
+                    .map(|id| Id::new((id).to_string()))
+                    .collect::<Vec<_>>(),
+            );
+        }
+    }
+
+    /// Resolves the icons of the results and library apps that are not cached
+    /// yet, in the background.
+    fn resolve_icons(&mut self) -> Task<Message> {
+        let sources = self
+            .launcher_items
+            .iter()
+            .flat_map(|item| item.icon.iter().chain(&item.category_icon))
+            .chain(self.library_apps.iter().map(|app| &app.icon));
+        let Some((generation, keys)) = self.icons.request(sources) else {
+            return Task::none();
+        };
+        let requested = keys.clone();
+        Task::perform(
+            async move {
+                tokio::task::spawn_blocking(move || icons::resolve(keys))
+                    .await
+                    .unwrap_or_default()
+            },
+            move |resolved| {
+                cosmic::action::app(Message::IconsResolved(generation, requested, resolved))
+            },
+        )
+    }
+
+    /// Lists completions for the file being typed if the input names files to
+    /// open with an app, returning whether it does.
+    fn complete_files(&mut self) -> bool {
+        let Some((query, args)) = open_with::split(&self.input_value)
+            .filter(|(query, _)| Mode::of(query).is_none() && self.dmenu.is_none())
+        else {
+            self.open_with = None;
+            self.file_completions.clear();
+            return false;
+        };
+
+        if self.open_with.is_none() {
+            // the best app listed for the query, whichever result is focused
+            let query = query.trim();
+            let Some(app) = self
+                .launcher_items
+                .iter()
+                .filter(|item| self.is_app(item))
+                .filter_map(|item| Some((fuzzy_score(query, &item.name)?, item)))
+                .max_by_key(|(score, _)| *score)
+                .map(|(_, item)| item.clone())
+            else {
+                return false;
+            };
+            self.open_with = Some(app);
+        }
+
+        let arg = open_with::parse_args(args).pop().unwrap_or_default();
+        self.file_completions = open_with::completions(&arg);
+        let list = self
+            .file_completions
+            .iter()
+            .enumerate()
+            .map(|(i, completion)| {
+                let icon = if completion.dir {
+                    "folder"
+                } else {
+                    "text-x-generic"
+                };
+                SearchResult {
+                    #[allow(clippy::cast_possible_truncation)]
+                    id: i as u32,
+                    name: completion.name.clone(),
+                    description: String::new(),
+                    icon: Some(IconSource::Name(icon.into())),
+                    category_icon: None,
+                    window: None,
+                }
+            })
+            .collect();
+        self.set_launcher_items(list);
+        self.publish_state();
+        true
+    }
+
+    /// Returns `true` if the result launches a desktop entry, rather than
+    /// being an open window, a desktop action, a script or fallback result, or
+    /// a result of a plugin picked by a prefix, such as calculator and file
+    /// results, or a dmenu entry.
+    fn is_app(&self, item: &SearchResult) -> bool {
+        item.window.is_none()
+            && !matches!(item.icon, Some(IconSource::Mime(_)))
+            && !providers::owns(item.id)
+            && !scripts::owns(item.id)
+            && !fallbacks::owns(item.id)
+            && Mode::of(&self.input_value).is_none()
+            && self.dmenu.is_none()
+    }
+
+    /// Returns `true` if the context menu is for an app, which can then be
+    /// launched on another GPU, workspace or output.
+    fn menu_is_for_app(&self) -> bool {
+        self.menu
+            .as_ref()
+            .and_then(|(id, _)| self.launcher_items.iter().find(|item| item.id == *id))
+            .is_some_and(|item| self.is_app(item))
+    }
+
+    /// Launches the app picked for opening files with the typed files, the
+    /// last one completed by the completion at `index` if there is one.
+    fn open_files(&mut self, index: usize) -> Task<Message> {
+        let Some(app) = self.open_with.take() else {
+            return Task::none();
+        };
+        let Some((_, args)) = open_with::split(&self.input_value) else {
+            return Task::none();
+        };
+
+        let mut args = open_with::parse_args(args);
+        if let (Some(last), Some(completion)) = (args.last_mut(), self.file_completions.get(index))
+        {
+            last.clone_from(&completion.arg);
+        }
+        self.pending_files = args
+            .iter()
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| open_with::resolve(arg))
+            .collect();
+        self.file_completions.clear();
+
+        if let Some(dbus) = &self.dbus {
+            dbus.activated(app.name.clone(), app.description.clone());
+        }
+        self.dispatch(launcher::Request::Activate(app.id))
+    }
+
+    /// Lists the results that providers attach to app results, such as
+    /// desktop actions, as sub-rows under them.
+    fn attach_sub_rows(&mut self, list: Vec<SearchResult>) -> Vec<SearchResult> {
+        self.sub_rows.clear();
+        if self.alt_tab || self.dmenu.is_some() || self.input_value.is_empty() {
+            return list;
+        }
+
+        let mut providers = self.providers.borrow_mut();
+        let mut attached = Vec::with_capacity(list.len());
+        for item in list {
+            let children = if self.is_app(&item) {
+                providers.children(&item)
+            } else {
+                Vec::new()
+            };
+            attached.push(item);
+            for child in children {
+                if self.sub_rows.insert(child.id) {
+                    attached.push(child);
+                }
+            }
+        }
+
+        // results found by name that are already listed under their app
+        let mut seen = HashSet::new();
+        attached.retain(|item| !self.sub_rows.contains(&item.id) || seen.insert(item.id));
+        attached
+    }
+
+    /// Returns the index of the result that the row at `i` is listed under, or
+    /// `i` if it is not a sub-row.
+    fn parent_row(&self, i: usize) -> usize {
+        (0..=i)
+            .rev()
+            .find(|&j| {
+                self.launcher_items
+                    .get(j)
+                    .is_none_or(|item| !self.sub_rows.contains(&item.id))
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the index of the `n`th sub-row under the focused result, or
+    /// under the result that the focused sub-row is listed under.
+    fn action_row(&self, n: usize) -> Option<usize> {
+        let parent = self.parent_row(self.focused);
+        let i = parent + 1 + n;
+        (parent + 1..=i)
+            .all(|j| {
+                self.launcher_items
+                    .get(j)
+                    .is_some_and(|item| self.sub_rows.contains(&item.id))
+            })
+            .then_some(i)
+    }
+
+    /// Returns the command line that runs a `t:` command in the configured
+    /// terminal, or `None` to leave the command to pop-launcher.
+    fn terminal_command(&self, command: &str) -> Option<String> {
+        let command = command.trim();
+        if self.dmenu.is_some() || command.is_empty() {
+            return None;
+        }
+        let template = terminal::resolve(self.config.terminal.as_deref()?)?;
+        Some(terminal::shell(template, command))
+    }
+
+    /// Lists the fallback actions in place of results if nothing matched a
+    /// normal search, one not handled by a mode, a script or the app picked
+    /// to open files with.
+    fn with_fallbacks(&self, list: Vec<SearchResult>) -> Vec<SearchResult> {
+        let query = self.input_value.trim();
+        if !list.is_empty()
+            || query.is_empty()
+            || self.alt_tab
+            || self.activate_first
+            || self.dmenu.is_some()
+            || self.open_with.is_some()
+            || Mode::of(&self.input_value).is_some()
+            || self.scripts.trigger(&self.input_value).is_some()
+        {
+            return list;
+        }
+        fallbacks::results(&self.config.fallbacks, query)
+    }
+
+    /// Returns `true` if the results list the fallback actions.
+    fn no_results(&self) -> bool {
+        self.launcher_items
+            .first()
+            .is_some_and(|item| fallbacks::owns(item.id))
+    }
+
+    fn run_fallback(&mut self, fallback: Fallback) -> Task<Message> {
+        let query = self.input_value.trim().to_string();
+        match fallback {
+            Fallback::Files => self.update(Message::InputChanged(format!(
+                "{}{query}",
+                Mode::Find.prefix()
+            ))),
+            Fallback::Terminal => match self.terminal_command(&query) {
+                Some(exec) => self.run_command(exec),
+                // left to pop-launcher's terminal plugin
+                None => {
+                    self.activate_first = true;
+                    let query = format!("{}{query}", Mode::Terminal.prefix());
+                    self.input_value.clone_from(&query);
+                    self.search(query)
+                }
+            },
+            _ => {
+                let Some(exec) = fallback.exec(&query) else {
+                    return Task::none();
+                };
+                self.run_command(exec)
+            }
+        }
+    }
+
+    /// Launches a command line that is not backed by a desktop entry.
+    fn run_command(&mut self, exec: String) -> Task<Message> {
+        self.request_launch(PendingLaunch {
+            app_id: exec_program(&exec).unwrap_or_default(),
+            exec,
+            gpu_preference: GpuPreference::Default,
+            terminal: false,
+        })
+    }
+
+    /// Requests an activation token to launch an app with, launching it
+    /// without one if the compositor does not answer in time.
+    fn request_launch(&mut self, pending: PendingLaunch) -> Task<Message> {
+        let launch = self.next_launch;
+        self.next_launch += 1;
+        self.launches.insert(launch, pending);
+
+        Task::batch([
+            request_token(Some(String::from(Self::APP_ID)), Some(self.window_id))
+                .map(move |token| cosmic::Action::App(Message::ActivationToken(launch, token))),
+            // the timer is created when the task runs, on the runtime
+            Task::perform(
+                async { tokio::time::sleep(TOKEN_TIMEOUT).await },
+                move |()| cosmic::action::app(Message::TokenTimeout(launch)),
+            ),
+        ])
+    }
+
+    fn launch(&self, token: Option<String>, pending: PendingLaunch) -> Task<Message> {
+        let PendingLaunch {
+            app_id,
+            exec,
+            gpu_preference,
+            terminal,
+        } = pending;
+        let gpu_envs = gpus::environment(&self.gpus, gpu_preference);
+        let (exec, terminal) = terminal::command(self.config.terminal.as_deref(), exec, terminal);
+        Task::perform(launch(token, app_id, exec, gpu_envs, terminal), |()| {
+            cosmic::action::app(Message::Hide)
+        })
+    }
+
+    /// Picks the GPU to launch an app on: the one chosen from the context menu,
+    /// then the one remembered for the app, then the backend's preference.
+    fn gpu_for(&mut self, app_id: &str, preference: GpuPreference) -> GpuPreference {
+        if let Some(index) = self.pending_gpu.take() {
+            if std::mem::take(&mut self.remember_gpu)
+                && let Some(gpu) = self.gpus.get(index)
+            {
+                let mut preferences = self.config.gpu_preferences.clone();
+                preferences.insert(app_id.to_string(), gpu.name.clone());
+                self.set_gpu_preferences(preferences);
+            }
+            #[allow(clippy::cast_possible_truncation)]
+            return GpuPreference::SpecificIdx(index as u32);
+        }
+
+        self.config
+            .gpu_preferences
+            .get(app_id)
+            .and_then(|name| self.gpus.iter().position(|gpu| &gpu.name == name))
+            .map_or(preference, |index| {
+                #[allow(clippy::cast_possible_truncation)]
+                GpuPreference::SpecificIdx(index as u32)
+            })
+    }
+
+    fn set_gpu_preferences(&mut self, preferences: BTreeMap<String, String>) {
+        match &self.config_handler {
+            Some(handler) => {
+                if let Err(why) = self.config.set_gpu_preferences(handler, preferences) {
+                    error!("failed to save GPU preferences: {why}");
+                }
+            }
+            None => self.config.gpu_preferences = preferences,
+        }
+    }
+
+    /// Reports a result that could not be launched, inline if the launcher is
+    /// open and as a notification otherwise.
+    fn launch_failed(&mut self, why: LaunchError) -> Task<Message> {
+        if self.surface_state == SurfaceState::Visible {
+            self.launch_error = Some(why);
+            return Task::none();
+        }
+
+        Task::future(async move {
+            if let Err(err) = notify(fl!("launch-failed"), why.to_string()).await {
+                error!("failed to show notification: {err}");
+            }
+        })
+        .discard()
+    }
+
+    /// Mirrors the state queried through the D-Bus interface.
+    fn publish_state(&self) {
+        if let Some(dbus) = &self.dbus {
+            dbus.set_state(dbus::State {
+                visible: self.surface_state == SurfaceState::Visible,
+                query: self.input_value.clone(),
+                results: u32::try_from(self.launcher_items.len()).unwrap_or(u32::MAX),
+                token_timeouts: self.token_timeouts,
+            });
+        }
+    }
+
     fn show(&mut self) -> Task<Message> {
         self.surface_state = SurfaceState::Visible;
-        cosmic::surface::surface_task(app_layer_shell(
+        self.scripts.scan();
+        self.publish_state();
+        // the launcher opens on the active output, which may have changed
+        let measure = self.measure_output();
+        let surface = cosmic::surface::surface_task(app_layer_shell(
             |app: &CosmicLauncher| LiveSettings {
                 padding: Some(app.layer_padding()),
                 corners: None,
@@ -252,25 +1056,51 @@ impl CosmicLauncher {
             move |app: &mut CosmicLauncher| SctkLayerSurfaceSettings {
                 id: app.window_id,
                 keyboard_interactivity: KeyboardInteractivity::Exclusive,
-                anchor: Anchor::TOP,
+                // the library fills the output
+                anchor: if app.library {
+                    Anchor::all()
+                } else {
+                    Anchor::TOP
+                },
                 namespace: "launcher".into(),
                 size: None,
-                size_limits: Limits::NONE.min_width(1.0).min_height(1.0).max_width(600.0),
+                size_limits: if app.library {
+                    Limits::NONE
+                } else {
+                    Limits::NONE
+                        .min_width(1.0)
+                        .min_height(1.0)
+                        .max_width(app.surface_width())
+                },
                 exclusive_zone: -1,
                 ..Default::default()
             },
             None,
         ))
-        .chain(self.handle_overlap())
+        .chain(self.handle_overlap());
+        Task::batch([measure, surface])
     }
 
     fn hide(&mut self) -> Task<Message> {
         self.input_value.clear();
+        self.input_cursor = InputCursor::End;
         self.focused = 0;
         self.alt_tab = false;
         self.alt_tab_released = false;
         self.queue.clear();
         self.hand_over.clear();
+        self.launch_error = None;
+        self.activate_first = false;
+        self.pending_gpu = None;
+        self.pending_placement = None;
+        self.open_with = None;
+        self.file_completions.clear();
+        self.pending_files.clear();
+        self.library = false;
+        if let Some(handle) = self.script_search.take() {
+            handle.abort();
+        }
+        self.scripts.matches.clear();
 
         self.request(launcher::Request::Close);
 
@@ -284,37 +1114,217 @@ impl CosmicLauncher {
         }
 
         self.surface_state = SurfaceState::Hidden;
+        self.publish_state();
 
         Task::batch(tasks)
     }
 
+    fn handle_task(&mut self, cmd: LauncherTasks) -> Task<Message> {
+        // handled by the invoking process in `run`
+        if matches!(
+            cmd,
+            LauncherTasks::Query { .. } | LauncherTasks::Dmenu { .. }
+        ) {
+            return Task::none();
+        }
+
+        let hidden = self.surface_state == SurfaceState::Hidden;
+        match cmd {
+            LauncherTasks::Show if !hidden => return Task::none(),
+            LauncherTasks::Toggle if !hidden => return self.hide(),
+            LauncherTasks::Close => return self.update(Message::Hide),
+            LauncherTasks::Library if self.library && !hidden => return self.hide(),
+            LauncherTasks::Library => return self.set_library(true),
+            LauncherTasks::Input {
+                input,
+                mode,
+                activate_first: true,
+            } => {
+                // searched without showing the surface, see `Response::Update`
+                let query = format!(
+                    "{}{}",
+                    mode.map_or("", Mode::prefix),
+                    input.unwrap_or_default()
+                );
+                self.activate_first = true;
+                self.input_value.clone_from(&query);
+                return self.search(query);
+            }
+            _ => {}
+        }
+
+        if hidden {
+            self.surface_state = SurfaceState::WaitingToBeShown;
+        }
+
+        match cmd {
+            LauncherTasks::AltTab => {
+                if self.alt_tab {
+                    if self.surface_state == SurfaceState::WaitingToBeShown
+                        || self.launcher_items.is_empty()
+                    {
+                        self.queue.push_back(Message::AltTab);
+                        return Task::none();
+                    }
+                    return self.update(Message::AltTab);
+                }
+
+                self.alt_tab = true;
+                self.alt_tab_released = false;
+                self.request(launcher::Request::Search(String::new()));
+                self.queue.push_back(Message::AltTab);
+            }
+            LauncherTasks::ShiftAltTab => {
+                if self.alt_tab {
+                    if self.surface_state == SurfaceState::WaitingToBeShown
+                        || self.launcher_items.is_empty()
+                    {
+                        self.queue.push_back(Message::ShiftAltTab);
+                        return Task::none();
+                    }
+                    return self.update(Message::ShiftAltTab);
+                }
+
+                self.alt_tab = true;
+                self.alt_tab_released = false;
+                self.request(launcher::Request::Search(String::new()));
+                self.queue.push_back(Message::ShiftAltTab);
+            }
+            LauncherTasks::Input { input, mode, .. } => {
+                self.request(launcher::Request::Search(String::new()));
+                if let Some(mode) = mode {
+                    self.hand_over.push_str(mode.prefix());
+                }
+                if let Some(input) = input {
+                    self.hand_over.push_str(&input);
+                };
+            }
+            LauncherTasks::Show | LauncherTasks::Toggle => {
+                self.request(launcher::Request::Search(String::new()));
+            }
+            LauncherTasks::Close
+            | LauncherTasks::Library
+            | LauncherTasks::Query { .. }
+            | LauncherTasks::Dmenu { .. } => {}
+        }
+        Task::none()
+    }
+
+    /// Number of results, or of apps in the library, that focus cycles through.
+    fn item_count(&self) -> usize {
+        if self.library {
+            self.library_matches().len()
+        } else {
+            self.launcher_items.len()
+        }
+    }
+
     fn focus_next(&mut self) {
-        if self.launcher_items.is_empty() {
+        let count = self.item_count();
+        if count == 0 {
             return;
         }
-        self.focused = (self.focused + 1) % self.launcher_items.len();
+        self.focused = (self.focused + 1) % count;
     }
 
     fn focus_previous(&mut self) {
-        if self.launcher_items.is_empty() {
+        let count = self.item_count();
+        if count == 0 {
             return;
         }
-        self.focused = (self.focused + self.launcher_items.len() - 1) % self.launcher_items.len();
+        self.focused = (self.focused + count - 1) % count;
     }
 
-    fn handle_overlap(&mut self) -> Task<Message> {
-        let mid_height = self.height / 2.;
-        self.margin = 0.;
+    /// Focuses the result at `i`, clamped to the first and last results.
+    fn focus_to(&mut self, i: isize) {
+        let last = self.item_count().saturating_sub(1);
+        self.focused = usize::try_from(i).unwrap_or(0).min(last);
+    }
 
-        for o in self.overlap.values() {
-            if self.margin + mid_height < o.y
-                || self.margin > o.y + o.height
-                || mid_height < o.y + o.height / 2.0
-            {
-                continue;
+    /// Number of results that fit in the visible part of the list.
+    fn page_size(&self) -> usize {
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let rows = (self.sizing.list_height / ROW_HEIGHT).floor() as usize;
+        rows.max(1)
+    }
+
+    /// Scrolls the list so that the focused result is in view.
+    ///
+    /// With accessibility enabled, the focused row also takes widget focus so
+    /// that screen readers announce it. The rows ignore keys, so typed text,
+    /// Enter and Space still only reach the search input, which is always
+    /// active.
+    fn snap_to_focused(&self) -> Task<Message> {
+        let snap = operation::snap_to(
+            SCROLLABLE.clone(),
+            RelativeOffset {
+                x: None,
+                y: Some((self.focused as f32 / (self.item_count() as f32 - 1.).max(1.)).max(0.0)),
+            },
+        );
+
+        #[cfg(feature = "a11y")]
+        if let Some(id) = self.result_ids.get(self.focused).filter(|_| !self.library) {
+            return Task::batch([snap, operation::focus(id.clone())]);
+        }
+        snap
+    }
+
+    /// Switches between the results list and the app library, which are shown
+    /// on differently anchored surfaces.
+    fn set_library(&mut self, library: bool) -> Task<Message> {
+        let mut tasks = Vec::with_capacity(3);
+        if self.surface_state == SurfaceState::Visible {
+            tasks.push(destroy_layer_surface(self.window_id));
+            if self.menu.take().is_some() {
+                tasks.push(commands::popup::destroy_popup(*MENU_ID));
             }
-            self.margin = o.y + o.height;
         }
+        // shown once the apps, or the results, are listed
+        self.surface_state = SurfaceState::WaitingToBeShown;
+        self.library = library;
+        self.input_value.clear();
+        self.focused = 0;
+        self.launch_error = None;
+        self.group_library();
+
+        if library {
+            tasks.push(Task::perform(
+                async {
+                    tokio::task::spawn_blocking(library::load)
+                        .await
+                        .unwrap_or_default()
+                },
+                |apps| cosmic::action::app(Message::LibraryLoaded(apps)),
+            ));
+        } else {
+            self.request(launcher::Request::Search(String::new()));
+        }
+        Task::batch(tasks)
+    }
+
+    /// Indices of the library apps matching the input, in the order shown.
+    fn library_matches(&self) -> Vec<usize> {
+        self.library_groups
+            .iter()
+            .flat_map(|(_, apps)| apps.iter().copied())
+            .collect()
+    }
+
+    /// Groups the library apps matching the input, after either changed.
+    fn group_library(&mut self) {
+        self.library_groups = library::groups(&self.library_apps, &self.input_value);
+    }
+
+    fn handle_overlap(&mut self) -> Task<Message> {
+        self.edges = overlap::edges(
+            self.output_size,
+            self.overlap
+                .values()
+                .filter(|layer| layer.avoided(&self.config))
+                .map(|layer| &layer.rect),
+        );
+        self.sizing = Sizing::new(self.output_size, self.edges);
         let mut cmds = Vec::with_capacity(2);
         cmds.push(set_padding::<()>(self.window_id, self.layer_padding()).discard());
         cmds.push(
@@ -346,24 +1356,346 @@ impl CosmicLauncher {
         Task::batch(cmds)
     }
 
+    /// Approximates where the focused row ends on the launcher surface, to
+    /// open its context menu from the keyboard.
+    #[allow(clippy::cast_precision_loss)]
+    fn focused_row_position(&self) -> Point<f32> {
+        let count = self.launcher_items.len() as f32;
+        let mut y = self.focused as f32 * ROW_HEIGHT;
+        if self.launcher_items.len() > SCROLL_MIN {
+            // scrolled as far as `snap_to_focused` does
+            let overflow = (count * ROW_HEIGHT - self.sizing.list_height).max(0.);
+            y -= overflow * self.focused as f32 / (count - 1.).max(1.);
+        }
+        let left = (self.edges.center_offset() * 2.).max(0.) + PADDING[1];
+        let x = if localize::rtl() {
+            left + ROW_PADDING
+        } else {
+            left + self.sizing.width - ROW_PADDING
+        };
+        let top = self.edges.top + SPACING + PADDING[0] + INPUT_HEIGHT + SPACING;
+        Point::new(x, top + y + ROW_HEIGHT / 2.)
+    }
+
+    /// Names what is answering the query: a pop-launcher plugin, a script or
+    /// the app picked to open files with.
+    fn mode_name(&self) -> String {
+        if let Some(app) = self.open_with.as_ref() {
+            return fl!("mode-open-with", app = app.name.as_str());
+        }
+        if let Some(dmenu) = self.dmenu.as_ref() {
+            return dmenu.prompt.clone().unwrap_or_else(|| "dmenu".into());
+        }
+        if let Some(trigger) = self.scripts.trigger(&self.input_value) {
+            return trigger.to_string();
+        }
+        Mode::of(&self.input_value).map_or_else(|| fl!("mode-apps"), Mode::name)
+    }
+
+    /// Shows the result count, the focused result, the active mode and the
+    /// keys that apply to it below the results.
+    fn view_footer(&self) -> Element<'_, Message> {
+        let total = self.launcher_items.len();
+        let no_results = self.no_results();
+        let mode = self.mode_name();
+        let status = if total == 0 || no_results {
+            fl!("footer-no-results", mode = mode.as_str())
+        } else {
+            fl!(
+                "footer-position",
+                mode = mode.as_str(),
+                position = self.focused.min(total - 1) + 1,
+                total = total
+            )
+        };
+
+        let mut hints = Vec::with_capacity(3);
+        if total > 0 {
+            hints.push(if self.dmenu.is_some() {
+                fl!("hint-select")
+            } else if self.open_with.is_some() {
+                fl!("hint-open")
+            } else {
+                fl!("hint-launch")
+            });
+            if !no_results {
+                hints.push(fl!("hint-complete"));
+            }
+            if self.dmenu.is_none() && self.open_with.is_none() && !no_results {
+                hints.push(fl!("hint-actions"));
+            }
+        }
+
+        let mut footer = vec![
+            text::caption(status).into(),
+            horizontal_space().width(Length::Fill).into(),
+            text::caption(hints.join("   ")).into(),
+        ];
+        if localize::rtl() {
+            footer.reverse();
+        }
+        row(footer)
+            .spacing(8)
+            .padding([0, 24])
+            .align_y(Alignment::Center)
+            .into()
+    }
+
+    /// Width of the launcher surface: its content and padding, plus the space
+    /// that centers it in the free area.
+    fn surface_width(&self) -> f32 {
+        self.sizing.width + 2. * PADDING[1] + self.edges.center_offset().abs() * 2.
+    }
+
+    /// Space left around the launcher to clear the edges and center it in the
+    /// free area, matching the spacing around its content in the view.
+    #[allow(clippy::cast_possible_truncation)]
     fn layer_padding(&self) -> IcedMargin {
+        if self.library {
+            return IcedMargin {
+                top: self.edges.top as i32,
+                right: self.edges.right as i32,
+                bottom: self.edges.bottom as i32,
+                left: self.edges.left as i32,
+            };
+        }
+        let offset = self.edges.center_offset() * 2.;
         IcedMargin {
-            #[allow(clippy::cast_possible_truncation)]
-            top: self.margin as i32 + 16,
+            top: (self.edges.top + SPACING) as i32,
+            left: offset.max(0.) as i32,
+            right: (-offset).max(0.) as i32,
             ..Default::default()
         }
     }
+
+    /// Shows every installed app in a grid grouped by category, filling the
+    /// area of the output left free by panels and docks.
+    fn view_library(&self) -> Element<'_, Message> {
+        let search = text_input::search_input(fl!("search-library"), &self.input_value)
+            .on_input(Message::InputChanged)
+            .on_paste(Message::InputChanged)
+            .on_submit(|_| Message::Activate(None))
+            .width(self.sizing.width)
+            .id(INPUT_ID.clone())
+            .always_active();
+
+        let free_width = self.output_size.width - self.edges.left - self.edges.right - 64.;
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let columns = (free_width / (TILE_WIDTH + 8.)).floor().max(1.) as usize;
+
+        let mut index = 0;
+        let mut sections = Column::new().spacing(24).width(Length::Fill);
+        for (category, apps) in &self.library_groups {
+            let mut grid = Column::new().spacing(8);
+            for chunk in apps.chunks(columns) {
+                grid = grid.push(
+                    row(chunk.iter().map(|&i| {
+                        let focused = index == self.focused;
+                        index += 1;
+                        self.library_tile(i, focused)
+                    }))
+                    .spacing(8),
+                );
+            }
+            sections = sections.push(column![text::heading(category.name()), grid].spacing(8));
+        }
+
+        let content = column![
+            container(search).center_x(Length::Fill),
+            scrollable(sections)
+                .id(SCROLLABLE.clone())
+                .height(Length::Fill),
+        ]
+        .spacing(24);
+
+        container(content)
+            .padding(Padding {
+                top: self.edges.top + 32.,
+                right: self.edges.right + 32.,
+                bottom: self.edges.bottom + 32.,
+                left: self.edges.left + 32.,
+            })
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .class(Container::Custom(Box::new(|theme| {
+                let t = theme.cosmic();
+                container::Style {
+                    text_color: Some(t.on_bg_color().into()),
+                    icon_color: Some(t.on_bg_color().into()),
+                    background: Some(Color::from(t.background(theme.transparent).base).into()),
+                    ..Default::default()
+                }
+            })))
+            .into()
+    }
+
+    fn library_tile(&self, i: usize, focused: bool) -> Element<'_, Message> {
+        let app = &self.library_apps[i];
+        let mut tile = Column::new()
+            .spacing(8)
+            .align_x(Alignment::Center)
+            .width(Length::Fill);
+        tile = tile.push(match self.icons.get(&app.icon) {
+            Some(handle) => Element::from(
+                icon(handle.clone())
+                    .width(Length::Fixed(64.))
+                    .height(Length::Fixed(64.)),
+            ),
+            None => placeholder(64.),
+        });
+        tile = tile.push(
+            text::body(&app.name)
+                .align_x(Horizontal::Center)
+                .ellipsize(Ellipsize::End(EllipsizeHeightLimit::Lines(2))),
+        );
+
+        button::custom(tile)
+            .width(Length::Fixed(TILE_WIDTH))
+            .padding(8)
+            .class(Button::IconVertical)
+            .selected(focused)
+            .on_press(Message::LibraryActivate(i))
+            .into()
+    }
+}
+
+/// Takes the place of an icon that is not resolved yet, so that rows keep their
+/// layout when it is swapped in.
+fn placeholder<'a>(size: f32) -> Element<'a, Message> {
+    vertical_space()
+        .width(Length::Fixed(size))
+        .height(Length::Fixed(size))
+        .into()
 }
 
 fn alt_tab_modifier_is_released(modifiers: Modifiers) -> bool {
     !modifiers.alt() && !modifiers.logo()
 }
 
-async fn launch(
+/// Why a desktop entry could not be launched.
+#[derive(Debug, Clone)]
+pub(crate) enum LaunchError {
+    /// The desktop file is missing or could not be parsed.
+    Load(PathBuf),
+    /// The entry, or the named action, has no `Exec` key.
+    NoExec(PathBuf),
+    /// The `Exec` line has no program to run.
+    BadExec(String),
+    /// The program is not an executable file nor found in `PATH`.
+    NotFound(String),
+}
+
+impl Display for LaunchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Load(path) => write!(f, "failed to load {}", path.display()),
+            Self::NoExec(path) => write!(f, "{} has nothing to execute", path.display()),
+            Self::BadExec(exec) => write!(f, "invalid command line: {exec}"),
+            Self::NotFound(program) => write!(f, "{program}: command not found"),
+        }
+    }
+}
+
+impl std::error::Error for LaunchError {}
+
+/// Loads a desktop entry, returning its id, the command line of the entry or
+/// of the named action, and whether it runs in a terminal.
+///
+/// The program of the command line is checked to exist, because spawning it
+/// does not report failures.
+pub(crate) fn desktop_exec(
+    path: PathBuf,
+    action_name: Option<String>,
+) -> Result<(String, String, bool), LaunchError> {
+    let entry = cosmic::desktop::load_desktop_file(&[], path.clone())
+        .ok_or_else(|| LaunchError::Load(path.clone()))?;
+    let exec = if let Some(action_name) = action_name {
+        entry
+            .desktop_actions
+            .into_iter()
+            .find(|action| action.name == action_name)
+            .map(|action| action.exec)
+    } else {
+        entry.exec
+    };
+    let exec = exec.ok_or(LaunchError::NoExec(path))?;
+
+    let program = exec_program(&exec)
+        .filter(|program| !program.is_empty() && !program.contains('='))
+        .ok_or_else(|| LaunchError::BadExec(exec.clone()))?;
+    if !program_exists(&program) {
+        return Err(LaunchError::NotFound(program));
+    }
+
+    Ok((entry.id.to_string(), exec, entry.terminal))
+}
+
+/// Returns the program of an `Exec` line, unquoting it as described in the
+/// desktop entry specification.
+pub(crate) fn exec_program(exec: &str) -> Option<String> {
+    let exec = exec.trim_start();
+    let Some(quoted) = exec.strip_prefix('"') else {
+        return exec.split_whitespace().next().map(str::to_string);
+    };
+
+    let mut program = String::new();
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(program),
+            '\\' => program.push(chars.next()?),
+            c => program.push(c),
+        }
+    }
+    // unterminated quote
+    None
+}
+
+pub(crate) fn program_exists(program: &str) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+
+    let is_executable = |path: &Path| {
+        path.metadata()
+            .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+    };
+
+    if program.contains('/') {
+        return is_executable(Path::new(program));
+    }
+    std::env::var_os("PATH").is_some_and(|paths| {
+        std::env::split_paths(&paths).any(|dir| is_executable(&dir.join(program)))
+    })
+}
+
+/// Shows a desktop notification, for failures the launcher is not open to show.
+async fn notify(summary: String, body: String) -> zbus::Result<()> {
+    let connection = zbus::Connection::session().await?;
+    connection
+        .call_method(
+            Some("org.freedesktop.Notifications"),
+            "/org/freedesktop/Notifications",
+            Some("org.freedesktop.Notifications"),
+            "Notify",
+            &(
+                fl!("app-name"),
+                0u32,
+                "dialog-error",
+                summary,
+                body,
+                Vec::<&str>::new(),
+                HashMap::<&str, zbus::zvariant::Value<'_>>::new(),
+                -1i32,
+            ),
+        )
+        .await?;
+    Ok(())
+}
+
+pub(crate) async fn launch(
     token: Option<String>,
     app_id: String,
     exec: String,
-    gpu: GpuPreference,
+    gpu_envs: Option<HashMap<String, String>>,
     terminal: bool,
 ) {
     let mut envs = Vec::new();
@@ -372,63 +1704,31 @@ async fn launch(
         envs.push(("DESKTOP_STARTUP_ID".to_string(), token));
     }
 
-    if let Some(gpu_envs) = try_get_gpu_envs(gpu).await {
+    if let Some(gpu_envs) = gpu_envs {
         envs.extend(gpu_envs);
     }
 
     cosmic::desktop::spawn_desktop_exec(exec, envs, Some(&app_id), terminal).await;
 }
 
-async fn try_get_gpu_envs(gpu: GpuPreference) -> Option<HashMap<String, String>> {
-    let connection = zbus::Connection::system().await.ok()?;
-    let proxy = switcheroo_control::SwitcherooControlProxy::new(&connection)
-        .await
-        .ok()?;
-    let gpus = proxy.get_gpus().await.ok()?;
-    match gpu {
-        GpuPreference::Default => gpus.into_iter().find(|gpu| gpu.default),
-        GpuPreference::NonDefault => gpus.into_iter().find(|gpu| !gpu.default),
-        GpuPreference::SpecificIdx(idx) => gpus.into_iter().nth(idx as usize),
-    }
-    .map(|gpu| gpu.environment)
-}
-
 impl cosmic::Application for CosmicLauncher {
     type Message = Message;
     type Executor = cosmic::executor::single::Executor;
     type Flags = Args;
     const APP_ID: &'static str = "com.system76.CosmicLauncher";
 
-    fn init(mut core: Core, _flags: Args) -> (Self, Task<Message>) {
+    fn init(mut core: Core, flags: Args) -> (Self, Task<Message>) {
         core.set_app_type(cosmic::core::AppType::System);
 
         core.set_keyboard_nav(false);
 
-        let mut app = CosmicLauncher {
-            core,
-            input_value: String::new(),
-            surface_state: SurfaceState::Hidden,
-            launcher_items: Vec::new(),
-            launcher_item_icon_handles: Vec::new(),
-            tx: None,
-            menu: None,
-            cursor_position: None,
-            focused: 0,
-            last_hide: Instant::now(),
-            alt_tab: false,
-            alt_tab_released: false,
-            window_id: SurfaceId::unique(),
-            queue: VecDeque::new(),
-            result_ids: (0..10)
-                .map(|id| Id::new(id.to_string()))
-                .collect::<Vec<_>>(),
-            margin: 0.,
-            overlap: HashMap::new(),
-            height: 800.,
-            needs_clear: false,
-            hand_over: String::default(),
-            dummy_id: None,
-        };
+        let (config_handler, config) = Config::load(Self::APP_ID);
+        let mut app =
+            CosmicLauncher::new(core, flags.dmenu, config, config_handler, Providers::new());
+        // in dmenu mode, show the choices as soon as they are listed
+        if app.dmenu.is_some() {
+            app.surface_state = SurfaceState::WaitingToBeShown;
+        }
         let task = app.create_dummy_layer_surface();
         app.needs_clear = false;
         (app, task)
@@ -445,17 +1745,57 @@ impl cosmic::Application for CosmicLauncher {
     #[allow(clippy::too_many_lines)]
     fn update(&mut self, message: Message) -> Task<Self::Message> {
         match message {
+            // the library filters its apps itself
+            Message::InputChanged(value) if self.library => {
+                self.input_cursor = self.input_cursor.edited(&value);
+                self.input_value = value;
+                self.focused = 0;
+                self.group_library();
+                return operation::snap_to(SCROLLABLE.clone(), RelativeOffset::START);
+            }
             Message::InputChanged(value) => {
+                self.input_cursor = self.input_cursor.edited(&value);
                 self.input_value.clone_from(&value);
+                self.launch_error = None;
+                let search = if self.complete_files() {
+                    self.resolve_icons()
+                } else {
+                    self.search(value)
+                };
                 self.focused = 0;
-                self.request(launcher::Request::Search(value));
-                return operation::snap_to(SCROLLABLE.clone(), RelativeOffset::START);
+                return Task::batch([
+                    search,
+                    operation::snap_to(SCROLLABLE.clone(), RelativeOffset::START),
+                ]);
             }
             Message::Backspace => {
-                self.input_value.pop();
-                self.focused = 0;
-                self.request(launcher::Request::Search(self.input_value.clone()));
-                return operation::snap_to(SCROLLABLE.clone(), RelativeOffset::START);
+                let mut input = self.input_value.clone();
+                input.pop();
+                return self.update(Message::InputChanged(input));
+            }
+            Message::TabPress if self.open_with.is_some() => {
+                let Some(completion) = self.file_completions.get(self.focused) else {
+                    return Task::none();
+                };
+                let Some((query, args)) = open_with::split(&self.input_value) else {
+                    return Task::none();
+                };
+                let mut args = open_with::parse_args(args);
+                if let Some(last) = args.last_mut() {
+                    last.clone_from(&completion.arg);
+                }
+                let input = open_with::join(query, &args);
+                return self.update(Message::InputChanged(input));
+            }
+            // a completed app name is followed by the files to open it with
+            Message::TabPress
+                if !self.alt_tab
+                    && self.launcher_items.get(self.focused).is_some_and(|item| {
+                        self.is_app(item) && item.name.eq_ignore_ascii_case(self.input_value.trim())
+                    }) =>
+            {
+                let input = format!("{}{} ", self.input_value.trim_end(), open_with::SEPARATOR);
+                return self.update(Message::InputChanged(input));
             }
             Message::TabPress if !self.alt_tab => {
                 let focused = self.focused;
@@ -473,45 +1813,155 @@ impl cosmic::Application for CosmicLauncher {
                     .unwrap_or_default();
 
                 if let Some(id) = self.launcher_items.get(i).map(|res| res.id) {
-                    self.request(launcher::Request::Complete(id));
+                    return self.dispatch(launcher::Request::Complete(id));
+                }
+            }
+            Message::Activate(i) if self.library => {
+                if let Some(&app) = self.library_matches().get(i.unwrap_or(self.focused)) {
+                    return self.update(Message::LibraryActivate(app));
                 }
             }
+            Message::Activate(i) if self.open_with.is_some() => {
+                return self.open_files(i.unwrap_or(self.focused));
+            }
             Message::Activate(i) => {
+                if let Some(command) = self.input_value.strip_prefix(Mode::Terminal.prefix())
+                    && let Some(exec) = self.terminal_command(command)
+                {
+                    return self.run_command(exec);
+                }
                 let alt_tab = self.alt_tab;
                 if let Some(item) = self.launcher_items.get(i.unwrap_or(self.focused)) {
-                    self.request(launcher::Request::Activate(item.id));
+                    let id = item.id;
+                    if let Some(dbus) = &self.dbus {
+                        dbus.activated(item.name.clone(), item.description.clone());
+                    }
+                    let task = self.dispatch(launcher::Request::Activate(id));
                     if alt_tab {
-                        return self.hide();
+                        return Task::batch([task, self.hide()]);
                     }
-                } else {
-                    return self.hide();
+                    return task;
                 }
+                return self.hide();
             }
             Message::Context(i) => {
                 if self.menu.take().is_some() {
                     return commands::popup::destroy_popup(*MENU_ID);
                 }
 
-                if let Some(item) = self.launcher_items.get(i) {
-                    self.request(launcher::Request::Context(item.id));
+                if let Some(id) = self.launcher_items.get(i).map(|item| item.id) {
+                    return self.dispatch(launcher::Request::Context(id));
+                }
+            }
+            Message::KeyboardContext
+                if !self.alt_tab
+                    && !self.library
+                    && self.dmenu.is_none()
+                    && self.open_with.is_none() =>
+            {
+                // the menu opens at the cursor, so move it to the focused row
+                self.cursor_position = Some(self.focused_row_position());
+                return self.update(Message::Context(self.focused));
+            }
+            Message::KeyboardContext => {}
+            Message::ActivateAction(n) => {
+                if let Some(i) = self.action_row(n) {
+                    return self.update(Message::Activate(Some(i)));
                 }
             }
             Message::CursorMoved(pos) => {
                 self.cursor_position = Some(pos);
             }
+            Message::Gpus(gpus) => {
+                self.gpus = gpus;
+            }
+            Message::LaunchOnGpu(id, index) => {
+                self.pending_gpu = Some(index);
+                let task = self.dispatch(launcher::Request::Activate(id));
+
+                if self.menu.take().is_some() {
+                    return Task::batch([task, commands::popup::destroy_popup(*MENU_ID)]);
+                }
+                return task;
+            }
+            Message::RememberGpu => {
+                self.remember_gpu = !self.remember_gpu;
+            }
+            Message::ActivateOn(placement) if !self.alt_tab => {
+                self.pending_placement = Some(placement);
+                return self.update(Message::Activate(None));
+            }
+            Message::ActivateOn(_) => {}
+            Message::LaunchOn(id, placement) => {
+                self.pending_placement = Some(placement);
+                let task = self.dispatch(launcher::Request::Activate(id));
+
+                if self.menu.take().is_some() {
+                    return Task::batch([task, commands::popup::destroy_popup(*MENU_ID)]);
+                }
+                return task;
+            }
+            Message::WaylandEvent(wayland::Event::Started(handle)) => {
+                self.wayland = Some(handle);
+            }
+            Message::WaylandEvent(wayland::Event::Layout(layout)) => {
+                self.layout = layout;
+            }
+            Message::Config(config) => {
+                self.config = config;
+                return self.handle_overlap();
+            }
+            Message::ToggleLibrary => {
+                if self.surface_state == SurfaceState::Visible
+                    && !self.alt_tab
+                    && self.dmenu.is_none()
+                {
+                    return self.set_library(!self.library);
+                }
+            }
+            Message::LibraryLoaded(apps) => {
+                self.library_apps = apps;
+                self.group_library();
+                let icons = self.resolve_icons();
+                if self.library && self.surface_state == SurfaceState::WaitingToBeShown {
+                    return Task::batch([icons, self.show()]);
+                }
+                return icons;
+            }
+            Message::LibraryActivate(i) => {
+                if let Some(app) = self.library_apps.get(i) {
+                    let response = pop_launcher::Response::DesktopEntry {
+                        path: app.path.clone(),
+                        gpu_preference: GpuPreference::Default,
+                        action_name: None,
+                    };
+                    return self
+                        .update(Message::LauncherEvent(launcher::Event::Response(response)));
+                }
+            }
             Message::MenuButton(i, context) => {
-                self.request(launcher::Request::ActivateContext(i, context));
+                let task = self.dispatch(launcher::Request::ActivateContext(i, context));
 
                 if self.menu.take().is_some() {
-                    return commands::popup::destroy_popup(*MENU_ID);
+                    return Task::batch([task, commands::popup::destroy_popup(*MENU_ID)]);
                 }
+                return task;
             }
             Message::Opened(size, window_id) => {
                 let mut tasks = Vec::with_capacity(3);
                 if let Some(dummy) = self.dummy_id
                     && dummy == window_id
                 {
-                    tasks.push(overlap_notify(window_id, true));
+                    if !self.output_measured {
+                        // the surface covering the output is only needed to measure it
+                        self.output_size = size;
+                        self.output_measured = true;
+                        tasks.push(destroy_layer_surface(dummy));
+                        tasks.push(self.create_dummy_layer_surface());
+                    } else {
+                        tasks.push(self.handle_overlap());
+                        tasks.push(overlap_notify(window_id, true));
+                    }
                 } else if self.dummy_id.is_none() {
                     tasks.push(overlap_notify(self.window_id, true));
                 }
@@ -531,7 +1981,23 @@ impl cosmic::Application for CosmicLauncher {
                 launcher::Event::ServiceIsClosed => {
                     self.request(launcher::Request::ServiceIsClosed);
                 }
+                launcher::Event::State(state) => {
+                    self.service_state = state;
+                    // no results will arrive to trigger showing the surface, so
+                    // show it now to display the retry row instead
+                    if state.is_unavailable()
+                        && self.surface_state == SurfaceState::WaitingToBeShown
+                    {
+                        return self.show();
+                    }
+                }
                 launcher::Event::Response(response) => match response {
+                    // the dmenu backend closes once, when the launcher is done
+                    pop_launcher::Response::Close if self.dmenu.is_some() => {
+                        return cosmic::iced::exit();
+                    }
+                    // keep a launch failure visible until it is dismissed
+                    pop_launcher::Response::Close if self.launch_error.is_some() => {}
                     pop_launcher::Response::Close => {
                         return self.hide();
                     }
@@ -542,6 +2008,7 @@ impl cosmic::Application for CosmicLauncher {
                         }
 
                         self.menu = Some((id, options));
+                        self.remember_gpu = false;
                         let Some(pos) = self.cursor_position.as_ref() else {
                             return Task::none();
                         };
@@ -551,126 +2018,93 @@ impl cosmic::Application for CosmicLauncher {
                             width: 1,
                             height: 1,
                         };
+                        // open leftward in right-to-left languages, away from the text
+                        let rtl = localize::rtl();
                         return commands::popup::get_popup(SctkPopupSettings {
-                                    parent: self.window_id,
-                                    id: *MENU_ID,
-                                    positioner: SctkPositioner {
-                                        size: None,
-                                        size_limits: Limits::NONE.min_width(1.0).min_height(1.0).max_width(300.0).max_height(800.0),
-                                        anchor_rect: rect,
-                                        anchor:
-                                            sctk::reexports::protocols::xdg::shell::client::xdg_positioner::Anchor::Right,
-                                        gravity: sctk::reexports::protocols::xdg::shell::client::xdg_positioner::Gravity::Right,
-                                        reactive: true,
-                                        ..Default::default()
-                                    },
-                                    grab: true,
-                                    parent_size: None,
-                                    close_with_children: false,
-                                    input_zone: None,
-                                });
+                            parent: self.window_id,
+                            id: *MENU_ID,
+                            positioner: SctkPositioner {
+                                size: None,
+                                size_limits: Limits::NONE
+                                    .min_width(1.0)
+                                    .min_height(1.0)
+                                    .max_width(300.0)
+                                    .max_height(800.0),
+                                anchor_rect: rect,
+                                anchor: if rtl {
+                                    xdg_positioner::Anchor::Left
+                                } else {
+                                    xdg_positioner::Anchor::Right
+                                },
+                                gravity: if rtl {
+                                    xdg_positioner::Gravity::Left
+                                } else {
+                                    xdg_positioner::Gravity::Right
+                                },
+                                reactive: true,
+                                ..Default::default()
+                            },
+                            grab: true,
+                            parent_size: None,
+                            close_with_children: false,
+                            input_zone: None,
+                        });
                     }
                     pop_launcher::Response::DesktopEntry {
                         path,
                         gpu_preference,
                         action_name,
-                    } => {
-                        if let Some(entry) = cosmic::desktop::load_desktop_file(&[], path) {
-                            let exec = if let Some(action_name) = action_name {
-                                entry
-                                    .desktop_actions
-                                    .into_iter()
-                                    .find(|action| action.name == action_name)
-                                    .map(|action| action.exec)
-                            } else {
-                                entry.exec
-                            };
-
-                            let Some(exec) = exec else {
-                                return Task::none();
-                            };
-                            return request_token(
-                                Some(String::from(Self::APP_ID)),
-                                Some(self.window_id),
-                            )
-                            .map(move |token| {
-                                cosmic::Action::App(Message::ActivationToken(
-                                    token,
-                                    entry.id.to_string(),
-                                    exec.clone(),
-                                    gpu_preference,
-                                    entry.terminal,
-                                ))
+                    } => match desktop_exec(path.clone(), action_name) {
+                        Ok((app_id, mut exec, terminal)) => {
+                            if !self.pending_files.is_empty() {
+                                let files = std::mem::take(&mut self.pending_files);
+                                exec = open_with::substitute(&exec, &files);
+                            }
+                            let gpu_preference = self.gpu_for(&app_id, gpu_preference);
+                            if let Some(placement) = self.pending_placement.take()
+                                && let Some(wayland) = &self.wayland
+                            {
+                                wayland.place(app_id.clone(), placement);
+                            }
+                            return self.request_launch(PendingLaunch {
+                                app_id,
+                                exec,
+                                gpu_preference,
+                                terminal,
                             });
                         }
-                    }
+                        Err(why) => {
+                            error!(path = %path.display(), error = %why, "failed to launch");
+                            return self.launch_failed(why);
+                        }
+                    },
+                    // the results list file completions instead
+                    pop_launcher::Response::Update(_) if self.open_with.is_some() => {}
                     pop_launcher::Response::Update(mut list) => {
                         if self.alt_tab && list.is_empty() {
                             return self.hide();
                         }
-                        if self.alt_tab || self.input_value.is_empty() {
+                        if self.dmenu.is_none() && (self.alt_tab || self.input_value.is_empty()) {
                             list.reverse();
                         }
+                        if !self.alt_tab && self.dmenu.is_none() {
+                            list.extend(self.providers.borrow_mut().query(&self.input_value));
+                            list.extend(self.scripts.matches.iter().map(|m| m.result.clone()));
+                        }
                         list.sort_by(|a, b| {
                             let a = i32::from(a.window.is_none());
                             let b = i32::from(b.window.is_none());
                             a.cmp(&b)
                         });
-                        self.launcher_items.splice(.., list);
-                        if self.result_ids.len() < self.launcher_items.len() {
-                            self.result_ids.extend(
-                                (self.result_ids.len()..self.launcher_items.len())
-                                    .map(|id| Id::new((id).to_string()))
-                                    .collect::<Vec<_>>(),
-                            );
-                        }
+                        let list = self.attach_sub_rows(list);
+                        let list = self.with_fallbacks(list);
+                        self.set_launcher_items(list);
 
-                        self.launcher_item_icon_handles.clear();
-                        self.launcher_item_icon_handles = self
-                            .launcher_items
-                            .iter()
-                            .map(|item| {
-                                item.icon.as_ref().map(|icon_source| match icon_source {
-                                    // Check if the name is actually a path
-                                    IconSource::Name(name) if name.contains('/') => {
-                                        let path = Path::new(&**name);
-                                        if path.exists() {
-                                            icon::from_path(path.into())
-                                        } else {
-                                            icon::from_name("application-default")
-                                                .prefer_svg(true)
-                                                .size(64)
-                                                .fallback(Some(IconFallback::Names(vec![
-                                                    "application-x-executable".into(),
-                                                ])))
-                                                .handle()
-                                        }
-                                    }
-                                    // Fetch icon by name
-                                    IconSource::Name(name) => icon::from_name(&**name)
-                                        .prefer_svg(true)
-                                        .size(64)
-                                        .fallback(Some(IconFallback::Names(vec![
-                                            "application-default".into(),
-                                            "application-x-executable".into(),
-                                        ])))
-                                        .handle(),
-                                    // By mime
-                                    IconSource::Mime(mime) => {
-                                        icon::from_name(mime.as_ref().replace('/', "-"))
-                                            .prefer_svg(true)
-                                            .size(64)
-                                            .fallback(Some(IconFallback::Names(vec![
-                                                "application-default".into(),
-                                                "application-x-executable".into(),
-                                            ])))
-                                            .handle()
-                                    }
-                                })
-                            })
-                            .collect();
+                        if std::mem::take(&mut self.activate_first) {
+                            return self.update(Message::Activate(Some(0)));
+                        }
 
-                        let mut cmds = Vec::new();
+                        let mut cmds = vec![self.resolve_icons()];
 
                         while let Some(element) = self.queue.pop_front() {
                             let updated = self.update(element);
@@ -682,23 +2116,66 @@ impl cosmic::Application for CosmicLauncher {
                         } else if self.surface_state == SurfaceState::WaitingToBeShown {
                             cmds.push(self.show());
                         }
+                        self.publish_state();
                         return Task::batch(cmds);
                     }
                     pop_launcher::Response::Fill(s) => {
                         self.input_value = s;
-                        self.request(launcher::Request::Search(self.input_value.clone()));
+                        self.input_cursor = InputCursor::End;
+                        return self.search(self.input_value.clone());
                     }
                 },
             },
+            Message::DbusEvent(e) => match e {
+                dbus::Event::Started(handle) => {
+                    self.dbus = Some(handle);
+                    self.publish_state();
+                }
+                dbus::Event::Show(query) => {
+                    if self.surface_state == SurfaceState::Hidden {
+                        return self.handle_task(LauncherTasks::Input {
+                            input: (!query.is_empty()).then_some(query),
+                            mode: None,
+                            activate_first: false,
+                        });
+                    }
+                    if !query.is_empty() {
+                        return self.update(Message::InputChanged(query));
+                    }
+                }
+                dbus::Event::Hide => {
+                    if self.surface_state != SurfaceState::Hidden {
+                        return self.update(Message::Hide);
+                    }
+                }
+                dbus::Event::Toggle => {
+                    return self.handle_task(LauncherTasks::Toggle);
+                }
+                dbus::Event::SetQuery(query) => {
+                    return self.update(Message::InputChanged(query));
+                }
+            },
             Message::Layer(LayerEvent::Done, id) if self.dummy_id == Some(id) => {
                 self.dummy_id = None;
             }
             Message::Output(event) => {
-                if matches!(event, OutputEvent::Created(_) | OutputEvent::InfoUpdate(_))
-                    && self.dummy_id.is_none()
+                let mut tasks = Vec::with_capacity(2);
+                if let OutputEvent::Created(Some(info)) | OutputEvent::InfoUpdate(info) = &event
+                    && !self.output_measured
+                    && let Some(size) = sizing::logical_size(info)
                 {
-                    return self.create_dummy_layer_surface();
+                    self.output_size = size;
+                    tasks.push(self.handle_overlap());
                 }
+                match event {
+                    // the mode or scale of the measured output may have changed
+                    OutputEvent::InfoUpdate(_) => tasks.push(self.measure_output()),
+                    OutputEvent::Created(_) if self.dummy_id.is_none() => {
+                        tasks.push(self.create_dummy_layer_surface());
+                    }
+                    _ => {}
+                }
+                return Task::batch(tasks);
             }
             Message::Layer(_, id) if id != self.window_id => {}
             Message::Layer(e, _) => match e {
@@ -720,9 +2197,14 @@ impl cosmic::Application for CosmicLauncher {
                         self.needs_clear = false;
                         self.overlap.clear();
                     }
-                    if exclusive > 0 || namespace == "Dock" || namespace == "Panel" {
-                        self.overlap.insert(identifier, logical_rect);
-                    }
+                    self.overlap.insert(
+                        identifier,
+                        overlap::Layer {
+                            namespace,
+                            exclusive,
+                            rect: logical_rect,
+                        },
+                    );
                     return self.handle_overlap();
                 }
                 OverlapNotifyEvent::OverlapLayerRemove { identifier } => {
@@ -731,6 +2213,27 @@ impl cosmic::Application for CosmicLauncher {
                 }
                 _ => {}
             },
+            Message::RetryService => {
+                self.request(launcher::Request::Retry);
+            }
+            Message::ScriptResults(query, matches) => {
+                if query != self.input_value {
+                    return Task::none();
+                }
+                self.script_search = None;
+                let mut list: Vec<_> = self
+                    .launcher_items
+                    .iter()
+                    .filter(|item| !scripts::owns(item.id) && !fallbacks::owns(item.id))
+                    .cloned()
+                    .collect();
+                list.extend(matches.iter().map(|m| m.result.clone()));
+                self.scripts.matches = matches;
+                let list = self.with_fallbacks(list);
+                self.set_launcher_items(list);
+                self.publish_state();
+                return self.resolve_icons();
+            }
             Message::CloseContextMenu => {
                 if self.menu.take().is_some() {
                     return commands::popup::destroy_popup(*MENU_ID);
@@ -747,69 +2250,110 @@ impl cosmic::Application for CosmicLauncher {
                     keyboard_nav::Action::FocusNext => {
                         self.focus_next();
                         // TODO ideally we could use an operation to scroll exactly to a specific widget.
-                        return operation::snap_to(
-                            SCROLLABLE.clone(),
-                            RelativeOffset {
-                                x: None,
-                                y: Some(
-                                    (self.focused as f32
-                                        / (self.launcher_items.len() as f32 - 1.).max(1.))
-                                    .max(0.0),
-                                ),
-                            },
-                        );
+                        return self.snap_to_focused();
                     }
                     keyboard_nav::Action::FocusPrevious => {
                         self.focus_previous();
-                        return operation::snap_to(
-                            SCROLLABLE.clone(),
-                            RelativeOffset {
-                                x: None,
-                                y: Some(
-                                    (self.focused as f32
-                                        / (self.launcher_items.len() as f32 - 1.).max(1.))
-                                    .max(0.0),
-                                ),
-                            },
-                        );
+                        return self.snap_to_focused();
                     }
                     keyboard_nav::Action::Escape => {
                         self.input_value.clear();
-                        self.request(launcher::Request::Search(String::new()));
+                        self.launch_error = None;
+                        return self.search(String::new());
                     }
                     _ => {}
                 };
             }
-            Message::ActivationToken(token, app_id, exec, dgpu, terminal) => {
-                return Task::perform(launch(token, app_id, exec, dgpu, terminal), |()| {
-                    cosmic::action::app(Message::Hide)
-                });
+            Message::ActivationToken(launch, token) => {
+                // already launched without a token
+                if let Some(pending) = self.launches.remove(&launch) {
+                    return self.launch(token, pending);
+                }
+            }
+            Message::TokenTimeout(launch) => {
+                if let Some(pending) = self.launches.remove(&launch) {
+                    self.token_timeouts += 1;
+                    self.publish_state();
+                    tracing::warn!(
+                        app_id = %pending.app_id,
+                        timeouts = self.token_timeouts,
+                        "no activation token after {TOKEN_TIMEOUT:?}, launching without one"
+                    );
+                    return self.launch(None, pending);
+                }
+            }
+            Message::Navigate(navigate) => {
+                let focused = self.focused.cast_signed();
+                let page = self.page_size().cast_signed();
+                // Home and End move the input cursor until it is at that edge
+                let no_cursor = self.alt_tab || self.input_value.is_empty();
+                let cursor = self.input_cursor;
+                match navigate {
+                    Navigate::PageUp => self.focus_to(focused - page),
+                    Navigate::PageDown => self.focus_to(focused + page),
+                    Navigate::Home { ctrl }
+                        if ctrl || no_cursor || cursor == InputCursor::Start =>
+                    {
+                        self.focus_to(0);
+                    }
+                    Navigate::End { ctrl } if ctrl || no_cursor || cursor == InputCursor::End => {
+                        self.focus_to(isize::MAX);
+                    }
+                    Navigate::Home { .. } => {
+                        self.input_cursor = InputCursor::Start;
+                        return Task::none();
+                    }
+                    Navigate::End { .. } => {
+                        self.input_cursor = InputCursor::End;
+                        return Task::none();
+                    }
+                }
+                return self.snap_to_focused();
+            }
+            Message::Wheel(delta) if self.alt_tab => {
+                // scrolling down, with a negative delta, moves to later windows
+                let steps = match delta {
+                    // a horizontal scroll or tilt has no vertical delta
+                    ScrollDelta::Lines { y, .. } if y == 0. => 0.,
+                    ScrollDelta::Lines { y, .. } => -y.signum(),
+                    ScrollDelta::Pixels { y, .. } => {
+                        self.wheel_pixels -= y;
+                        let steps = (self.wheel_pixels / WHEEL_STEP).trunc();
+                        self.wheel_pixels -= steps * WHEEL_STEP;
+                        steps
+                    }
+                };
+                #[allow(clippy::cast_possible_truncation)]
+                let steps = steps as isize;
+                if steps != 0 {
+                    let count = self.item_count().cast_signed().max(1);
+                    self.focused = (self.focused.cast_signed() + steps)
+                        .rem_euclid(count)
+                        .cast_unsigned();
+                    return self.snap_to_focused();
+                }
+            }
+            Message::Wheel(_) => {}
+            Message::InputCursorMoved => {
+                if !self.input_value.is_empty() {
+                    self.input_cursor = InputCursor::Inside;
+                }
+            }
+            Message::IconsResolved(generation, requested, resolved) => {
+                self.icons.insert(generation, &requested, resolved);
+            }
+            Message::IconTheme(theme) => {
+                if self.icons.set_theme(theme) {
+                    return self.resolve_icons();
+                }
             }
             Message::AltTab => {
                 self.focus_next();
-                return operation::snap_to(
-                    SCROLLABLE.clone(),
-                    RelativeOffset {
-                        x: None,
-                        y: Some(
-                            (self.focused as f32 / (self.launcher_items.len() as f32 - 1.).max(1.))
-                                .max(0.0),
-                        ),
-                    },
-                );
+                return self.snap_to_focused();
             }
             Message::ShiftAltTab => {
                 self.focus_previous();
-                return operation::snap_to(
-                    SCROLLABLE.clone(),
-                    RelativeOffset {
-                        x: None,
-                        y: Some(
-                            (self.focused as f32 / (self.launcher_items.len() as f32 - 1.).max(1.))
-                                .max(0.0),
-                        ),
-                    },
-                );
+                return self.snap_to_focused();
             }
             Message::AltRelease => {
                 if self.alt_tab {
@@ -852,53 +2396,7 @@ impl cosmic::Application for CosmicLauncher {
                     return Task::none();
                 };
 
-                if self.surface_state == SurfaceState::Hidden {
-                    self.surface_state = SurfaceState::WaitingToBeShown;
-                }
-
-                match cmd {
-                    LauncherTasks::AltTab => {
-                        if self.alt_tab {
-                            if self.surface_state == SurfaceState::WaitingToBeShown
-                                || self.launcher_items.is_empty()
-                            {
-                                self.queue.push_back(Message::AltTab);
-                                return Task::none();
-                            }
-                            return self.update(Message::AltTab);
-                        }
-
-                        self.alt_tab = true;
-                        self.alt_tab_released = false;
-                        self.request(launcher::Request::Search(String::new()));
-                        self.queue.push_back(Message::AltTab);
-                    }
-                    LauncherTasks::ShiftAltTab => {
-                        if self.alt_tab {
-                            if self.surface_state == SurfaceState::WaitingToBeShown
-                                || self.launcher_items.is_empty()
-                            {
-                                self.queue.push_back(Message::ShiftAltTab);
-                                return Task::none();
-                            }
-                            return self.update(Message::ShiftAltTab);
-                        }
-
-                        self.alt_tab = true;
-                        self.alt_tab_released = false;
-                        self.request(launcher::Request::Search(String::new()));
-                        self.queue.push_back(Message::ShiftAltTab);
-                    }
-                    LauncherTasks::Input { input } => {
-                        self.request(launcher::Request::Search(String::new()));
-                        if let Some(input) = input {
-                            self.hand_over.push_str(&input);
-                        };
-                    }
-                    LauncherTasks::Close => {
-                        return self.update(Message::Hide);
-                    }
-                }
+                return self.handle_task(cmd);
             }
             Details::Open { .. } => {}
         }
@@ -911,8 +2409,16 @@ impl cosmic::Application for CosmicLauncher {
 
     #[allow(clippy::too_many_lines)]
     fn view_window(&self, id: SurfaceId) -> Element<'_, Self::Message> {
+        if id == self.window_id && self.library {
+            return self.view_library();
+        }
         if id == self.window_id {
-            let launcher_entry = text_input::search_input(fl!("type-to-search"), &self.input_value)
+            let placeholder = self
+                .dmenu
+                .as_ref()
+                .and_then(|dmenu| dmenu.prompt.clone())
+                .unwrap_or_else(|| fl!("type-to-search"));
+            let launcher_entry = text_input::search_input(placeholder, &self.input_value)
                 .on_input(Message::InputChanged)
                 .on_paste(Message::InputChanged)
                 .on_submit(|_| Message::Activate(None))
@@ -924,10 +2430,17 @@ impl cosmic::Application for CosmicLauncher {
                     focused: Box::new(|theme| theme.focused(&cosmic::theme::TextInput::Search)),
                     disabled: Box::new(|theme| theme.disabled(&cosmic::theme::TextInput::Search)),
                 })
-                .width(600.)
+                .width(self.sizing.width)
                 .id(INPUT_ID.clone())
                 .always_active();
 
+            // rows are mirrored in right-to-left languages
+            let rtl = localize::rtl();
+            let (start, end) = if rtl {
+                (Horizontal::Right, Horizontal::Left)
+            } else {
+                (Horizontal::Left, Horizontal::Right)
+            };
             let buttons: Vec<_> = self
                 .launcher_items
                 .iter()
@@ -942,7 +2455,7 @@ impl cosmic::Application for CosmicLauncher {
                     let name = Column::with_children(name.lines().map(|line| {
                         text::body(line.to_string())
                             .ellipsize(Ellipsize::End(EllipsizeHeightLimit::Lines(1)))
-                            .align_x(Horizontal::Left)
+                            .align_x(start)
                             .align_y(Vertical::Center)
                             .class(cosmic::theme::Text::Custom(|t| {
                                 let theme = t.cosmic();
@@ -957,7 +2470,7 @@ impl cosmic::Application for CosmicLauncher {
                     let desc = Column::with_children(desc.lines().map(|line| {
                         text::caption(line.to_string())
                             .ellipsize(Ellipsize::End(EllipsizeHeightLimit::Lines(1)))
-                            .align_x(Horizontal::Left)
+                            .align_x(start)
                             .align_y(Vertical::Center)
                             .class(theme::Text::Custom(|t| {
                                 let theme = t.cosmic();
@@ -973,22 +2486,8 @@ impl cosmic::Application for CosmicLauncher {
                     if !self.alt_tab
                         && let Some(source) = item.category_icon.as_ref()
                     {
-                        let icon_handle = match source {
-                            IconSource::Name(name) => {
-                                if Path::new(name.as_ref()).exists() {
-                                    icon::from_path(Path::new(name.as_ref()).into())
-                                } else {
-                                    icon::from_name(name.as_ref()).handle()
-                                }
-                            }
-
-                            IconSource::Mime(mime) => {
-                                icon::from_name(mime.as_ref().replace('/', "-")).handle()
-                            }
-                        };
-
-                        button_content.push(
-                            icon(icon_handle)
+                        button_content.push(match self.icons.get(source) {
+                            Some(handle) => icon(handle.clone())
                                 .width(Length::Fixed(16.0))
                                 .height(Length::Fixed(16.0))
                                 .class(cosmic::theme::Svg::Custom(Rc::new(|theme| {
@@ -997,24 +2496,36 @@ impl cosmic::Application for CosmicLauncher {
                                     }
                                 })))
                                 .into(),
-                        );
+                            None => placeholder(16.0),
+                        });
                     }
-                    if let Some(Some(icon_handle)) = self.launcher_item_icon_handles.get(i) {
-                        button_content.push(
-                            icon(icon_handle.clone())
-                                .width(Length::Fixed(32.0))
-                                .height(Length::Fixed(32.0))
+                    let sub_row = self.sub_rows.contains(&item.id);
+                    if let Some(source) = item.icon.as_ref() {
+                        let size = if sub_row { 24.0 } else { 32.0 };
+                        button_content.push(match self.icons.get(source) {
+                            Some(handle) => icon(handle.clone())
+                                .width(Length::Fixed(size))
+                                .height(Length::Fixed(size))
                                 .into(),
-                        );
+                            None => placeholder(size),
+                        });
                     }
 
                     button_content.push(column![name, desc].width(Length::FillPortion(5)).into());
-                    if i < 10 {
+                    // the actions of the focused app have shortcuts of their own
+                    let parent = self.parent_row(i);
+                    let shortcut =
+                        if sub_row && i - parent <= 9 && parent == self.parent_row(self.focused) {
+                            Some(format!("Ctrl + Shift + {}", i - parent))
+                        } else {
+                            (i < 10).then(|| format!("Ctrl + {}", (i + 1) % 10))
+                        };
+                    if let Some(shortcut) = shortcut {
                         button_content.push(
                             container(
-                                text::body(format!("Ctrl + {}", (i + 1) % 10))
+                                text::body(shortcut)
                                     .align_y(Vertical::Center)
-                                    .align_x(Horizontal::Right)
+                                    .align_x(end)
                                     .class(theme::Text::Custom(|t| {
                                         let theme = t.cosmic();
                                         cosmic::iced::widget::text::Style {
@@ -1026,79 +2537,101 @@ impl cosmic::Application for CosmicLauncher {
                             .width(Length::FillPortion(1))
                             .center_y(Length::Shrink)
                             .align_y(Vertical::Center)
-                            .align_x(Horizontal::Right)
+                            .align_x(end)
                             .into(),
                         );
                     }
+                    if rtl {
+                        button_content.reverse();
+                    }
                     let is_focused = i == self.focused;
-                    let btn = mouse_area(
-                        cosmic::widget::button::custom(
-                            row(button_content).spacing(8).align_y(Alignment::Center),
-                        )
-                        .id(self.result_ids[i].clone())
-                        .width(Length::Fill)
-                        .on_press(Message::Activate(Some(i)))
-                        .padding([8, 24])
-                        .class(Button::Custom {
-                            active: Box::new(move |focused, theme| {
-                                let focused = is_focused || focused;
-                                let rad_s = theme.cosmic().corner_radii.radius_s;
-                                let a = if focused {
-                                    button::Catalog::hovered(theme, focused, focused, &Button::Text)
-                                } else {
-                                    button::Catalog::active(theme, focused, focused, &Button::Text)
-                                };
-                                button::Style {
-                                    border_radius: rad_s.into(),
-                                    outline_width: 0.0,
-                                    ..a
-                                }
-                            }),
-                            hovered: Box::new(move |focused, theme| {
-                                let focused = is_focused || focused;
-                                let rad_s = theme.cosmic().corner_radii.radius_s;
-
-                                let text = button::Catalog::hovered(
-                                    theme,
-                                    focused,
-                                    focused,
-                                    &Button::Text,
-                                );
-                                button::Style {
-                                    border_radius: rad_s.into(),
-                                    outline_width: 0.0,
-                                    ..text
-                                }
-                            }),
-                            disabled: Box::new(|theme| {
-                                let rad_s = theme.cosmic().corner_radii.radius_s;
-
-                                let text = button::Catalog::disabled(theme, &Button::Text);
-                                button::Style {
-                                    border_radius: rad_s.into(),
-                                    outline_width: 0.0,
-                                    ..text
-                                }
-                            }),
-                            pressed: Box::new(move |focused, theme| {
-                                let focused = is_focused || focused;
-                                let rad_s = theme.cosmic().corner_radii.radius_s;
-
-                                let text = button::Catalog::pressed(
-                                    theme,
-                                    focused,
-                                    focused,
-                                    &Button::Text,
-                                );
-                                button::Style {
-                                    border_radius: rad_s.into(),
-                                    outline_width: 0.0,
-                                    ..text
-                                }
-                            }),
-                        }),
+                    let result_button = cosmic::widget::button::custom(
+                        row(button_content).spacing(8).align_y(Alignment::Center),
                     )
-                    .on_right_release(Message::Context(i));
+                    .id(self.result_ids[i].clone())
+                    .width(Length::Fill)
+                    .on_press(Message::Activate(Some(i)))
+                    .padding(if sub_row {
+                        let indent = Padding::from([4., ROW_PADDING]);
+                        if rtl {
+                            indent.right(64.0)
+                        } else {
+                            indent.left(64.0)
+                        }
+                    } else {
+                        Padding::from([8., ROW_PADDING])
+                    })
+                    .class(Button::Custom {
+                        active: Box::new(move |focused, theme| {
+                            let focused = is_focused || focused;
+                            let rad_s = theme.cosmic().corner_radii.radius_s;
+                            let a = if focused {
+                                button::Catalog::hovered(theme, focused, focused, &Button::Text)
+                            } else {
+                                button::Catalog::active(theme, focused, focused, &Button::Text)
+                            };
+                            button::Style {
+                                border_radius: rad_s.into(),
+                                outline_width: 0.0,
+                                ..a
+                            }
+                        }),
+                        hovered: Box::new(move |focused, theme| {
+                            let focused = is_focused || focused;
+                            let rad_s = theme.cosmic().corner_radii.radius_s;
+
+                            let text =
+                                button::Catalog::hovered(theme, focused, focused, &Button::Text);
+                            button::Style {
+                                border_radius: rad_s.into(),
+                                outline_width: 0.0,
+                                ..text
+                            }
+                        }),
+                        disabled: Box::new(|theme| {
+                            let rad_s = theme.cosmic().corner_radii.radius_s;
+
+                            let text = button::Catalog::disabled(theme, &Button::Text);
+                            button::Style {
+                                border_radius: rad_s.into(),
+                                outline_width: 0.0,
+                                ..text
+                            }
+                        }),
+                        pressed: Box::new(move |focused, theme| {
+                            let focused = is_focused || focused;
+                            let rad_s = theme.cosmic().corner_radii.radius_s;
+
+                            let text =
+                                button::Catalog::pressed(theme, focused, focused, &Button::Text);
+                            button::Style {
+                                border_radius: rad_s.into(),
+                                outline_width: 0.0,
+                                ..text
+                            }
+                        }),
+                    });
+                    // read out as "name, description, 3 of 12" when focused
+                    #[cfg(feature = "a11y")]
+                    let result_button = {
+                        let (name, desc) = if item.window.is_some() {
+                            (&item.description, &item.name)
+                        } else {
+                            (&item.name, &item.description)
+                        };
+                        let position = fl!(
+                            "result-position",
+                            position = i + 1,
+                            total = self.launcher_items.len()
+                        );
+                        let description = if desc.is_empty() {
+                            position
+                        } else {
+                            format!("{desc}, {position}")
+                        };
+                        result_button.name(name.clone()).description(description)
+                    };
+                    let btn = mouse_area(result_button).on_right_release(Message::Context(i));
                     if i == self.launcher_items.len() - 1 {
                         vec![btn.into()]
                     } else {
@@ -1109,30 +2642,69 @@ impl cosmic::Application for CosmicLauncher {
 
             let mut content = if self.alt_tab {
                 Column::new()
-                    .max_width(600)
-                    .spacing(16)
-                    .width(Length::Fixed(600.))
+                    .max_width(self.sizing.width)
+                    .spacing(SPACING)
+                    .width(Length::Fixed(self.sizing.width))
                     .height(Length::Shrink)
             } else {
                 column![launcher_entry]
-                    .max_width(600)
+                    .max_width(self.sizing.width)
                     .width(Length::Shrink)
                     .height(Length::Shrink)
-                    .spacing(16)
+                    .spacing(SPACING)
             };
 
+            if let Some(why) = self.launch_error.as_ref().filter(|_| !self.alt_tab) {
+                content = content.push(
+                    column![
+                        text::body(fl!("launch-failed")),
+                        text::caption(why.to_string()),
+                    ]
+                    .padding([8, 24])
+                    .spacing(4),
+                );
+            }
+
+            if self.no_results() {
+                content = content.push(
+                    container(
+                        text::body(fl!("no-results", query = self.input_value.trim()))
+                            .width(Length::Fill)
+                            .align_x(start)
+                            .ellipsize(Ellipsize::End(EllipsizeHeightLimit::Lines(1))),
+                    )
+                    .padding([0, 24]),
+                );
+            }
+
             if buttons.len() > SCROLL_MIN {
                 content = content.push(
                     container(scrollable(components::list::column(buttons)).id(SCROLLABLE.clone()))
-                        .max_height(504),
+                        .max_height(self.sizing.list_height),
                 );
             } else if !buttons.is_empty() {
                 content = content.push(components::list::column(buttons));
+            } else if !self.alt_tab && self.service_state.is_unavailable() {
+                content = content.push(
+                    row![
+                        text::body(fl!("backend-unavailable")).width(Length::Fill),
+                        button::text(fl!("retry")).on_press(Message::RetryService),
+                    ]
+                    .spacing(8)
+                    .padding([8, 24])
+                    .align_y(Alignment::Center),
+                );
             }
 
+            if self.config.footer && !self.alt_tab {
+                content = content.push(self.view_footer());
+            }
+
+            let offset = self.edges.center_offset() * 2.;
             let window = Column::new()
-                .push(vertical_space().height(Length::Fixed(self.margin + 16.)))
-                .push(
+                .push(vertical_space().height(Length::Fixed(self.edges.top + SPACING)))
+                .push(row![
+                    horizontal_space().width(Length::Fixed(offset.max(0.))),
                     container(id_container(content, MAIN_ID.clone()))
                         .width(Length::Shrink)
                         .height(Length::Shrink)
@@ -1155,8 +2727,9 @@ impl cosmic::Application for CosmicLauncher {
                                 snap: true,
                             }
                         })))
-                        .padding([24, 32]),
-                );
+                        .padding(PADDING),
+                    horizontal_space().width(Length::Fixed((-offset).max(0.))),
+                ]);
 
             let autosize = autosize::autosize(
                 if self.menu.is_some() {
@@ -1179,13 +2752,58 @@ impl cosmic::Application for CosmicLauncher {
                     .height(Length::Fixed(1.0))
                     .into();
             };
-            let list_column = Column::with_children(options.iter().map(|option| {
+            let mut list_column = Column::with_children(options.iter().map(|option| {
                 menu_button(text::body(&option.name))
                     .on_press(Message::MenuButton(*i, option.id))
                     .into()
             }))
             .padding([8, 0]);
 
+            let app = self.menu_is_for_app();
+            if app && self.gpus.len() > 1 {
+                list_column = list_column.push(divider::horizontal::light());
+                for (index, gpu) in self.gpus.iter().enumerate() {
+                    list_column = list_column.push(
+                        menu_button(text::body(fl!("launch-on-gpu", gpu = gpu.name.as_str())))
+                            .on_press(Message::LaunchOnGpu(*i, index)),
+                    );
+                }
+                let mut remember = row![text::body(fl!("remember-gpu")).width(Length::Fill)]
+                    .spacing(8)
+                    .align_y(Alignment::Center);
+                if self.remember_gpu {
+                    remember =
+                        remember.push(icon::from_name("object-select-symbolic").size(16).icon());
+                }
+                list_column =
+                    list_column.push(menu_button(remember).on_press(Message::RememberGpu));
+            }
+
+            if app && (self.layout.workspaces > 0 || self.layout.outputs.len() > 1) {
+                list_column = list_column.push(divider::horizontal::light());
+                for index in 0..self.layout.workspaces {
+                    list_column = list_column.push(
+                        menu_button(text::body(fl!("launch-on-workspace", number = index + 1)))
+                            .on_press(Message::LaunchOn(*i, Placement::Workspace(index))),
+                    );
+                }
+                list_column = list_column.push(
+                    menu_button(text::body(fl!("launch-on-new-workspace")))
+                        .on_press(Message::LaunchOn(*i, Placement::NewWorkspace)),
+                );
+                if self.layout.outputs.len() > 1 {
+                    for output in &self.layout.outputs {
+                        list_column = list_column.push(
+                            menu_button(text::body(fl!(
+                                "launch-on-output",
+                                output = output.as_str()
+                            )))
+                            .on_press(Message::LaunchOn(*i, Placement::Output(output.clone()))),
+                        );
+                    }
+                }
+            }
+
             return container(
                 container(scrollable(list_column)).class(theme::Container::custom(|theme| {
                     let cosmic = theme.cosmic();
@@ -1217,8 +2835,24 @@ impl cosmic::Application for CosmicLauncher {
     }
 
     fn subscription(&self) -> Subscription<Self::Message> {
+        let backend = match &self.dmenu {
+            Some(dmenu) => dmenu::subscription(dmenu).map(Message::LauncherEvent),
+            None => Subscription::batch(vec![
+                launcher::subscription(0).map(Message::LauncherEvent),
+                dbus::subscription(0).map(Message::DbusEvent),
+                gpus::subscription(0).map(Message::Gpus),
+                wayland::subscription(0).map(Message::WaylandEvent),
+                self.core()
+                    .watch_config::<Config>(Self::APP_ID)
+                    .map(|update| Message::Config(update.config)),
+            ]),
+        };
+
         Subscription::batch(vec![
-            launcher::subscription(0).map(Message::LauncherEvent),
+            backend,
+            self.core()
+                .watch_config::<CosmicTk>(cosmic::config::ID)
+                .map(|update| Message::IconTheme(update.config.icon_theme)),
             listen_raw(|e, status, id| match e {
                 cosmic::iced::Event::PlatformSpecific(PlatformSpecific::Wayland(
                     wayland::Event::Layer(e, _, layer_id),
@@ -1252,6 +2886,14 @@ impl cosmic::Application for CosmicLauncher {
                     Key::Character(c) if modifiers.control() && (c == "n" || c == "j") => {
                         Some(Message::KeyboardNav(keyboard_nav::Action::FocusNext))
                     }
+                    Key::Character(c) if modifiers.control() && c == "l" => {
+                        Some(Message::ToggleLibrary)
+                    }
+                    Key::Character(c) if modifiers.control() && modifiers.shift() => c
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|n| (1..10).contains(n))
+                        .map(|n| Message::ActivateAction(n - 1)),
                     Key::Character(c) if modifiers.control() => {
                         let nums = (1..10)
                             .map(|n| (n.to_string(), ((n + 10) % 10) - 1))
@@ -1259,12 +2901,35 @@ impl cosmic::Application for CosmicLauncher {
                         nums.iter()
                             .find_map(|n| (n.0 == c).then(|| Message::Activate(Some(n.1))))
                     }
+                    // Alt+1 to Alt+9 launch on that workspace, and Alt+0 on a new one
+                    Key::Character(c) if modifiers.alt() && !modifiers.control() => {
+                        match c.parse::<usize>() {
+                            Ok(0) => Some(Message::ActivateOn(Placement::NewWorkspace)),
+                            Ok(n) if n < 10 => {
+                                Some(Message::ActivateOn(Placement::Workspace(n - 1)))
+                            }
+                            _ => None,
+                        }
+                    }
                     Key::Named(Named::ArrowUp) => {
                         Some(Message::KeyboardNav(keyboard_nav::Action::FocusPrevious))
                     }
                     Key::Named(Named::ArrowDown) => {
                         Some(Message::KeyboardNav(keyboard_nav::Action::FocusNext))
                     }
+                    Key::Named(Named::ArrowLeft | Named::ArrowRight) => {
+                        Some(Message::InputCursorMoved)
+                    }
+                    Key::Named(Named::PageUp) => Some(Message::Navigate(Navigate::PageUp)),
+                    Key::Named(Named::PageDown) => Some(Message::Navigate(Navigate::PageDown)),
+                    Key::Named(Named::Home) => Some(Message::Navigate(Navigate::Home {
+                        ctrl: modifiers.control(),
+                    })),
+                    Key::Named(Named::End) => Some(Message::Navigate(Navigate::End {
+                        ctrl: modifiers.control(),
+                    })),
+                    Key::Named(Named::F10) if modifiers.shift() => Some(Message::KeyboardContext),
+                    Key::Named(Named::ContextMenu) => Some(Message::KeyboardContext),
                     Key::Named(Named::Escape) => Some(Message::Hide),
                     Key::Named(Named::Tab) => Some(Message::TabPress),
                     Key::Named(Named::Backspace)
@@ -1274,6 +2939,9 @@ impl cosmic::Application for CosmicLauncher {
                     }
                     _ => None,
                 },
+                cosmic::iced::Event::Mouse(iced::mouse::Event::WheelScrolled { delta }) => {
+                    Some(Message::Wheel(delta))
+                }
                 cosmic::iced::Event::Mouse(iced::mouse::Event::CursorMoved { position }) => {
                     Some(Message::CursorMoved(position))
                 }
@@ -1288,3 +2956,708 @@ impl cosmic::Application for CosmicLauncher {
         ])
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{app, result};
+    use crate::subscriptions::launcher::fake::FakeBackend;
+    use futures::StreamExt;
+    use futures::stream::BoxStream;
+    use pop_launcher::{Request, Response};
+
+    /// Drives the app against a fake backend on a runtime with a paused clock.
+    struct Harness {
+        rt: tokio::runtime::Runtime,
+        events: BoxStream<'static, launcher::Event>,
+        fake: FakeBackend,
+        app: CosmicLauncher,
+    }
+
+    impl Harness {
+        fn new() -> Self {
+            let rt = tokio::runtime::Builder::new_current_thread()
+                .enable_time()
+                .enable_io()
+                .start_paused(true)
+                .build()
+                .unwrap();
+            let fake = FakeBackend::new();
+            let events = rt.block_on(async { launcher::service_with(fake.clone()).boxed() });
+            // neither the user's config nor the installed apps' desktop actions
+            let mut app = CosmicLauncher::new(
+                Core::default(),
+                None,
+                Config::default(),
+                None,
+                Providers::default(),
+            );
+            let _task = app.create_dummy_layer_surface();
+            app.needs_clear = false;
+
+            let mut harness = Self {
+                rt,
+                events,
+                fake,
+                app,
+            };
+            harness.pump();
+            harness
+        }
+
+        /// Feeds backend events to the app until the backend goes idle.
+        fn pump(&mut self) {
+            loop {
+                let events = &mut self.events;
+                let event = self.rt.block_on(async {
+                    tokio::time::timeout(Duration::from_secs(1), events.next())
+                        .await
+                        .ok()
+                        .flatten()
+                });
+                let Some(event) = event else {
+                    break;
+                };
+                let _task = self.app.update(Message::LauncherEvent(event));
+            }
+        }
+
+        fn update(&mut self, message: Message) {
+            let _task = self.app.update(message);
+            self.pump();
+        }
+
+        fn task(&mut self, cmd: LauncherTasks) {
+            let _task = self.app.handle_task(cmd);
+            self.pump();
+        }
+
+        fn names(&self) -> Vec<&str> {
+            self.app
+                .launcher_items
+                .iter()
+                .map(|item| item.name.as_str())
+                .collect()
+        }
+    }
+
+    fn window_result(id: u32, name: &str) -> SearchResult {
+        SearchResult {
+            window: Some((0, id)),
+            ..result(id, name)
+        }
+    }
+
+    #[test]
+    fn search_updates_results() {
+        let mut h = Harness::new();
+        h.fake.reply([Response::Update(vec![
+            result(0, "Firefox"),
+            window_result(1, "Files"),
+        ])]);
+
+        h.update(Message::InputChanged("f".into()));
+
+        assert_eq!(h.app.input_value, "f");
+        // open windows are sorted above applications
+        assert_eq!(h.names(), ["Files", "Firefox"]);
+        assert!(matches!(
+            h.fake.requests().last(),
+            Some(Request::Search(s)) if s == "f"
+        ));
+    }
+
+    #[test]
+    fn activate_sends_focused_result() {
+        let mut h = Harness::new();
+        h.fake.reply([Response::Update(vec![
+            result(10, "Firefox"),
+            result(11, "Files"),
+        ])]);
+        h.update(Message::InputChanged("f".into()));
+
+        h.update(Message::KeyboardNav(keyboard_nav::Action::FocusNext));
+        h.update(Message::Activate(None));
+
+        assert!(matches!(
+            h.fake.requests().last(),
+            Some(Request::Activate(11))
+        ));
+    }
+
+    #[test]
+    fn context_menu_activates_option() {
+        let mut h = Harness::new();
+        h.fake
+            .reply([Response::Update(vec![result(3, "Firefox")])])
+            .reply([Response::Context {
+                id: 3,
+                options: vec![ContextOption {
+                    id: 0,
+                    name: "New Window".into(),
+                }],
+            }]);
+        h.update(Message::InputChanged("fire".into()));
+
+        h.update(Message::Context(0));
+        assert!(matches!(&h.app.menu, Some((3, options)) if options.len() == 1));
+
+        h.update(Message::MenuButton(3, 0));
+        assert!(h.app.menu.is_none());
+        assert!(matches!(
+            h.fake.requests().last(),
+            Some(Request::ActivateContext { id: 3, context: 0 })
+        ));
+    }
+
+    #[test]
+    fn only_apps_offer_launch_options() {
+        let mut h = Harness::new();
+        let options = vec![ContextOption {
+            id: 0,
+            name: "Close".into(),
+        }];
+        h.fake
+            .reply([Response::Update(vec![
+                window_result(3, "Firefox"),
+                result(4, "Firefox"),
+            ])])
+            .reply([Response::Context {
+                id: 3,
+                options: options.clone(),
+            }])
+            .reply([Response::Context { id: 4, options }]);
+        h.update(Message::InputChanged("fire".into()));
+
+        h.update(Message::Context(0));
+        assert!(!h.app.menu_is_for_app());
+
+        h.update(Message::Context(0));
+        h.update(Message::Context(1));
+        assert!(h.app.menu_is_for_app());
+    }
+
+    #[test]
+    fn shortcuts_activate_actions_of_focused_app() {
+        let mut h = Harness::new();
+        h.fake.reply([Response::Update(vec![
+            result(0, "Firefox"),
+            result(5, "New Window"),
+            result(6, "New Private Window"),
+            result(1, "Files"),
+        ])]);
+        h.update(Message::InputChanged("f".into()));
+        h.app.sub_rows = HashSet::from([5, 6]);
+
+        let requests = h.fake.requests().len();
+        h.app.focused = 3;
+        h.update(Message::ActivateAction(0));
+        assert_eq!(h.fake.requests().len(), requests);
+
+        h.app.focused = 1;
+        assert_eq!(h.app.action_row(2), None);
+        h.update(Message::ActivateAction(1));
+        assert!(matches!(
+            h.fake.requests().last(),
+            Some(Request::Activate(6))
+        ));
+    }
+
+    #[test]
+    fn keyboard_opens_focused_context_menu() {
+        let mut h = Harness::new();
+        h.fake
+            .reply([Response::Update(vec![
+                result(3, "Firefox"),
+                result(4, "Files"),
+            ])])
+            .reply([Response::Context {
+                id: 4,
+                options: vec![ContextOption {
+                    id: 0,
+                    name: "New Window".into(),
+                }],
+            }]);
+        h.update(Message::InputChanged("fi".into()));
+        h.update(Message::KeyboardNav(keyboard_nav::Action::FocusNext));
+
+        h.update(Message::KeyboardContext);
+        assert!(matches!(&h.app.menu, Some((4, _))));
+        assert!(h.app.cursor_position.is_some());
+    }
+
+    #[test]
+    fn mode_follows_query_prefix() {
+        assert!(matches!(Mode::of("= 2 + 2"), Some(Mode::Calc)));
+        assert!(matches!(Mode::of("~/Documents"), Some(Mode::Files)));
+        assert!(matches!(Mode::of("/etc"), Some(Mode::Files)));
+        assert!(matches!(Mode::of("t:htop"), Some(Mode::Terminal)));
+        assert!(matches!(Mode::of("?"), Some(Mode::Help)));
+        assert!(Mode::of("firefox").is_none());
+    }
+
+    #[test]
+    fn offers_fallbacks_when_nothing_matches() {
+        let mut h = Harness::new();
+        h.fake
+            .reply([Response::Update(Vec::new())])
+            .reply([Response::Update(vec![result(0, "notes.txt")])]);
+        h.app.config.fallbacks = vec![Fallback::Web, Fallback::Files];
+        h.update(Message::InputChanged("notes".into()));
+
+        assert!(h.app.no_results());
+        let offered: Vec<_> = h
+            .app
+            .launcher_items
+            .iter()
+            .map(|item| fallbacks::get(&h.app.config.fallbacks, item.id))
+            .collect();
+        assert_eq!(offered, [Some(Fallback::Web), Some(Fallback::Files)]);
+
+        h.update(Message::KeyboardNav(keyboard_nav::Action::FocusNext));
+        h.update(Message::Activate(None));
+        assert_eq!(h.app.input_value, "find notes");
+        assert!(matches!(
+            h.fake.requests().last(),
+            Some(Request::Search(query)) if query == "find notes"
+        ));
+        assert_eq!(h.names(), ["notes.txt"]);
+    }
+
+    #[test]
+    fn prefixed_queries_get_no_fallbacks() {
+        let mut h = Harness::new();
+        h.fake
+            .reply([Response::Update(Vec::new())])
+            .reply([Response::Update(Vec::new())]);
+        h.app.config.fallbacks = vec![Fallback::Web];
+
+        h.update(Message::InputChanged("= 2 +".into()));
+        assert!(h.app.launcher_items.is_empty());
+
+        h.update(Message::InputChanged("t:htop".into()));
+        assert!(h.app.launcher_items.is_empty());
+    }
+
+    #[test]
+    fn fill_replaces_input_and_searches() {
+        let mut h = Harness::new();
+        h.fake
+            .reply([Response::Update(vec![result(0, "Firefox")])])
+            .reply([Response::Fill("firefox ".into())])
+            .reply([Response::Update(Vec::new())]);
+        h.update(Message::InputChanged("fi".into()));
+
+        h.update(Message::CompleteFocusedId(h.app.result_ids[0].clone()));
+
+        assert_eq!(h.app.input_value, "firefox ");
+        assert!(h.app.launcher_items.is_empty());
+        assert!(matches!(
+            &h.fake.requests()[..],
+            [.., Request::Complete(0), Request::Search(s)] if s == "firefox "
+        ));
+    }
+
+    #[test]
+    fn close_response_hides_launcher() {
+        let mut h = Harness::new();
+        h.fake
+            .reply([Response::Update(vec![result(0, "Firefox")])])
+            .reply([Response::Close]);
+
+        h.task(LauncherTasks::Show);
+        assert_eq!(h.app.surface_state, SurfaceState::Visible);
+
+        h.update(Message::Activate(None));
+        assert_eq!(h.app.surface_state, SurfaceState::Hidden);
+        assert!(h.app.input_value.is_empty());
+    }
+
+    #[test]
+    fn toggle_opens_and_closes() {
+        let mut h = Harness::new();
+        h.fake.reply([Response::Update(vec![result(0, "Firefox")])]);
+
+        h.task(LauncherTasks::Toggle);
+        assert_eq!(h.app.surface_state, SurfaceState::Visible);
+
+        h.task(LauncherTasks::Show);
+        assert_eq!(h.app.surface_state, SurfaceState::Visible);
+
+        h.task(LauncherTasks::Toggle);
+        assert_eq!(h.app.surface_state, SurfaceState::Hidden);
+    }
+
+    #[test]
+    fn input_activate_first_launches_top_hit() {
+        let mut h = Harness::new();
+        h.fake.reply([Response::Update(vec![
+            result(7, "Terminal"),
+            result(8, "Terminal Settings"),
+        ])]);
+
+        h.task(LauncherTasks::Input {
+            input: Some("htop".into()),
+            mode: Some(Mode::Terminal),
+            activate_first: true,
+        });
+
+        assert_eq!(h.app.surface_state, SurfaceState::Hidden);
+        assert!(matches!(
+            &h.fake.requests()[..],
+            [.., Request::Search(s), Request::Activate(7)] if s == "t:htop"
+        ));
+    }
+
+    #[test]
+    fn remembers_gpu_chosen_from_menu() {
+        let mut h = Harness::new();
+        let gpus = h.rt.block_on(async {
+            let (connection, _server) = gpus::fake::serve(&[
+                ("Intel Graphics", &["DRI_PRIME", "0"], true),
+                ("NVIDIA GeForce", &["__NV_PRIME_RENDER_OFFLOAD", "1"], false),
+            ])
+            .await
+            .unwrap();
+            gpus::list(&connection).await.unwrap()
+        });
+        h.update(Message::Gpus(gpus));
+        h.fake
+            .reply([Response::Update(vec![result(3, "Blender")])])
+            .reply([Response::Context {
+                id: 3,
+                options: vec![ContextOption {
+                    id: 0,
+                    name: "New Window".into(),
+                }],
+            }]);
+        h.update(Message::InputChanged("blend".into()));
+        h.update(Message::Context(0));
+
+        h.update(Message::RememberGpu);
+        h.update(Message::LaunchOnGpu(3, 1));
+        assert!(matches!(
+            h.fake.requests().last(),
+            Some(Request::Activate(3))
+        ));
+        assert!(matches!(
+            h.app.gpu_for("blender.desktop", GpuPreference::Default),
+            GpuPreference::SpecificIdx(1)
+        ));
+
+        // later launches of the same app use the remembered GPU
+        assert!(matches!(
+            h.app.gpu_for("blender.desktop", GpuPreference::Default),
+            GpuPreference::SpecificIdx(1)
+        ));
+        assert!(matches!(
+            h.app.gpu_for("firefox.desktop", GpuPreference::Default),
+            GpuPreference::Default
+        ));
+    }
+
+    #[test]
+    fn opens_files_with_picked_app() {
+        let mut h = Harness::new();
+        h.fake.reply([Response::Update(vec![
+            result(5, "Image Viewer"),
+            result(6, "Document Scanner"),
+        ])]);
+        h.update(Message::InputChanged("image".into()));
+
+        // the focus is on another result
+        h.app.focused = 1;
+        h.update(Message::InputChanged("image >".into()));
+        assert_eq!(h.app.open_with.as_ref().map(|app| app.id), Some(5));
+
+        h.update(Message::InputChanged(
+            r#"image > /nonexistent/a\ b.png "/nonexistent/c.png""#.into(),
+        ));
+        assert!(h.app.launcher_items.is_empty());
+        h.update(Message::Activate(None));
+
+        assert_eq!(
+            h.app.pending_files,
+            ["/nonexistent/a b.png", "/nonexistent/c.png"]
+        );
+        assert!(matches!(
+            &h.fake.requests()[..],
+            [.., Request::Search(s), Request::Activate(5)] if s == "image"
+        ));
+    }
+
+    #[test]
+    fn dmenu_entries_are_not_opened_with() {
+        let mut h = Harness::new();
+        h.app.dmenu = Some(Dmenu::new(None, Vec::new()));
+        h.fake
+            .reply([Response::Update(vec![result(0, "Firefox")])])
+            .reply([Response::Update(Vec::new())]);
+        h.update(Message::InputChanged("firefox".into()));
+
+        h.update(Message::TabPress);
+        assert_eq!(h.app.input_value, "firefox");
+
+        h.update(Message::InputChanged("firefox > ~/".into()));
+        assert!(h.app.open_with.is_none());
+        assert!(h.app.file_completions.is_empty());
+    }
+
+    #[test]
+    fn runs_commands_in_configured_terminal() {
+        let mut h = Harness::new();
+        h.app.config.terminal = Some("sh -c".into());
+        h.fake
+            .reply([Response::Update(vec![result(2, "run htop")])]);
+        h.update(Message::InputChanged("t:htop".into()));
+
+        h.update(Message::Activate(None));
+
+        // run by the terminal rather than activated through pop-launcher
+        let execs: Vec<_> = h
+            .app
+            .launches
+            .values()
+            .map(|pending| pending.exec.as_str())
+            .collect();
+        assert_eq!(execs, [r#"sh -c "htop""#]);
+        assert!(matches!(
+            h.fake.requests().last(),
+            Some(Request::Search(s)) if s == "t:htop"
+        ));
+    }
+
+    #[test]
+    fn launches_without_token_after_timeout() {
+        let mut h = Harness::new();
+        let _task = h.app.request_launch(PendingLaunch {
+            app_id: "firefox".into(),
+            exec: "firefox".into(),
+            gpu_preference: GpuPreference::Default,
+            terminal: false,
+        });
+
+        h.update(Message::TokenTimeout(0));
+        assert!(h.app.launches.is_empty());
+        assert_eq!(h.app.token_timeouts, 1);
+
+        // the late token must not launch the app a second time
+        h.update(Message::ActivationToken(0, Some("token".into())));
+        h.update(Message::TokenTimeout(0));
+        assert_eq!(h.app.token_timeouts, 1);
+    }
+
+    #[test]
+    fn library_filters_and_launches_apps() {
+        let mut h = Harness::new();
+        h.task(LauncherTasks::Library);
+        assert!(h.app.library);
+        h.update(Message::LibraryLoaded(vec![
+            app("Files", library::Category::System),
+            app("Firefox", library::Category::Network),
+        ]));
+        assert_eq!(h.app.surface_state, SurfaceState::Visible);
+
+        let searches = h.fake.requests().len();
+        h.update(Message::InputChanged("fx".into()));
+        assert_eq!(h.fake.requests().len(), searches);
+        assert_eq!(h.app.library_matches(), [1]);
+
+        h.update(Message::Activate(None));
+        assert!(matches!(
+            &h.app.launch_error,
+            Some(LaunchError::Load(path)) if path.ends_with("Firefox.desktop")
+        ));
+    }
+
+    #[test]
+    fn pages_and_jumps_through_results() {
+        let mut h = Harness::new();
+        let list: Vec<_> = (0..40).map(|i| result(i, &format!("app {i}"))).collect();
+        h.fake.reply([Response::Update(list)]);
+        h.update(Message::InputChanged("app".into()));
+        let page = h.app.page_size();
+
+        h.update(Message::Navigate(Navigate::PageDown));
+        assert_eq!(h.app.focused, page);
+        h.update(Message::Navigate(Navigate::PageUp));
+        h.update(Message::Navigate(Navigate::PageUp));
+        assert_eq!(h.app.focused, 0);
+
+        // the input cursor is at the end after typing
+        h.update(Message::Navigate(Navigate::End { ctrl: false }));
+        assert_eq!(h.app.focused, 39);
+        h.update(Message::Navigate(Navigate::PageDown));
+        assert_eq!(h.app.focused, 39);
+
+        // moves the input cursor to the start first
+        h.update(Message::Navigate(Navigate::Home { ctrl: false }));
+        assert_eq!(h.app.focused, 39);
+        h.update(Message::Navigate(Navigate::Home { ctrl: false }));
+        assert_eq!(h.app.focused, 0);
+
+        // and off the end with Left
+        h.update(Message::Navigate(Navigate::End { ctrl: false }));
+        h.update(Message::InputCursorMoved);
+        h.update(Message::Navigate(Navigate::End { ctrl: false }));
+        assert_eq!(h.app.focused, 0);
+        h.update(Message::Navigate(Navigate::End { ctrl: true }));
+        assert_eq!(h.app.focused, 39);
+        h.update(Message::Navigate(Navigate::Home { ctrl: true }));
+        assert_eq!(h.app.focused, 0);
+    }
+
+    #[test]
+    fn wheel_moves_alt_tab_focus() {
+        let mut h = Harness::new();
+        let list: Vec<_> = (0..3).map(|i| result(i, &format!("window {i}"))).collect();
+        h.fake.reply([Response::Update(list)]);
+        h.update(Message::InputChanged("window".into()));
+        h.app.alt_tab = true;
+
+        h.update(Message::Wheel(ScrollDelta::Lines { x: 0., y: -1. }));
+        assert_eq!(h.app.focused, 1);
+        h.update(Message::Wheel(ScrollDelta::Pixels { x: 0., y: 30. }));
+        assert_eq!(h.app.focused, 1);
+        h.update(Message::Wheel(ScrollDelta::Pixels { x: 0., y: 30. }));
+        assert_eq!(h.app.focused, 0);
+        h.update(Message::Wheel(ScrollDelta::Lines { x: 0., y: 1. }));
+        assert_eq!(h.app.focused, 2);
+        h.update(Message::Wheel(ScrollDelta::Lines { x: 1., y: 0. }));
+        assert_eq!(h.app.focused, 2);
+    }
+
+    #[test]
+    fn alt_number_activates_onto_workspace() {
+        let mut h = Harness::new();
+        h.fake
+            .reply([Response::Update(vec![result(4, "Terminal")])]);
+        h.update(Message::InputChanged("term".into()));
+
+        h.update(Message::ActivateOn(Placement::Workspace(2)));
+
+        assert_eq!(h.app.pending_placement, Some(Placement::Workspace(2)));
+        assert!(matches!(
+            h.fake.requests().last(),
+            Some(Request::Activate(4))
+        ));
+    }
+
+    #[test]
+    fn launch_failure_stays_visible() {
+        let mut h = Harness::new();
+        h.fake
+            .reply([Response::Update(vec![result(0, "Broken")])])
+            .reply([
+                Response::DesktopEntry {
+                    path: "/nonexistent/broken.desktop".into(),
+                    gpu_preference: GpuPreference::Default,
+                    action_name: None,
+                },
+                Response::Close,
+            ]);
+
+        h.task(LauncherTasks::Show);
+        h.update(Message::Activate(None));
+
+        assert_eq!(h.app.surface_state, SurfaceState::Visible);
+        assert!(matches!(h.app.launch_error, Some(LaunchError::Load(_))));
+
+        h.update(Message::InputChanged("b".into()));
+        assert!(h.app.launch_error.is_none());
+    }
+
+    #[test]
+    fn exec_program_unquotes() {
+        assert_eq!(exec_program("firefox %u").as_deref(), Some("firefox"));
+        assert_eq!(
+            exec_program(r#""/opt/My App/run" --flag"#).as_deref(),
+            Some("/opt/My App/run")
+        );
+        assert_eq!(
+            exec_program(r#""say \"hi\"""#).as_deref(),
+            Some(r#"say "hi""#)
+        );
+        assert_eq!(exec_program(r#""unterminated"#), None);
+        assert_eq!(exec_program("  "), None);
+    }
+
+    #[test]
+    fn alt_tab_release_before_shown_activates_after_update() {
+        let mut h = Harness::new();
+        h.fake.reply([Response::Update(vec![
+            window_result(1, "Terminal"),
+            window_result(2, "Browser"),
+        ])]);
+
+        // the modifier is released before any results arrive
+        let _task = h.app.handle_task(LauncherTasks::AltTab);
+        let _task = h.app.update(Message::AltRelease);
+        assert!(h.app.alt_tab_released);
+        h.pump();
+
+        // results are reversed in alt-tab mode, and the queued alt-tab moves
+        // the focus to the second one
+        assert!(
+            h.fake
+                .requests()
+                .iter()
+                .any(|r| matches!(r, Request::Activate(1)))
+        );
+        assert_eq!(h.app.surface_state, SurfaceState::Hidden);
+        assert!(!h.app.alt_tab);
+    }
+
+    #[test]
+    fn measuring_surface_is_replaced_once_opened() {
+        let mut h = Harness::new();
+        let measuring = h.app.dummy_id.unwrap();
+
+        h.update(Message::Opened(Size::new(1920., 1080.), measuring));
+        let dummy = h.app.dummy_id.unwrap();
+        assert_ne!(dummy, measuring);
+        assert!(h.app.output_measured);
+
+        h.update(Message::Opened(Size::new(1., 1.), dummy));
+        assert_eq!(h.app.output_size, Size::new(1920., 1080.));
+        assert_eq!(h.app.dummy_id, Some(dummy));
+    }
+
+    #[test]
+    fn output_is_measured_again_when_shown() {
+        let mut h = Harness::new();
+        let measuring = h.app.dummy_id.unwrap();
+        h.update(Message::Opened(Size::new(1920., 1080.), measuring));
+        let dummy = h.app.dummy_id.unwrap();
+
+        // shown on a smaller output
+        let _task = h.app.show();
+        let measuring = h.app.dummy_id.unwrap();
+        assert_ne!(measuring, dummy);
+        assert!(!h.app.output_measured);
+
+        h.update(Message::Opened(Size::new(1366., 768.), measuring));
+        assert_eq!(h.app.output_size, Size::new(1366., 768.));
+        assert!(h.app.output_measured);
+    }
+
+    #[test]
+    fn input_task_hands_over_query_once_opened() {
+        let mut h = Harness::new();
+        h.task(LauncherTasks::Input {
+            input: Some("term".into()),
+            mode: None,
+            activate_first: false,
+        });
+        assert_eq!(h.app.hand_over, "term");
+
+        h.update(Message::Opened(Size::ZERO, h.app.window_id));
+
+        assert!(h.app.hand_over.is_empty());
+        assert_eq!(h.app.input_value, "term");
+        assert!(matches!(
+            h.fake.requests().last(),
+            Some(Request::Search(s)) if s == "term"
+        ));
+    }
+}
@@ -204,6 +204,10 @@ where
         shell: &mut Shell<'_, Message>,
         viewport: &Rectangle,
     ) {
+        #[cfg(feature = "a11y")]
+        if !forwards(event) {
+            return;
+        }
         for ((child, state), layout) in self
             .children
             .iter_mut()
@@ -311,8 +315,8 @@ where
         layout: Layout<'_>,
         state: &Tree,
         cursor: mouse::Cursor,
-    ) -> iced_accessibility::A11yTree {
-        use iced_accessibility::A11yTree;
+    ) -> cosmic::iced_accessibility::A11yTree {
+        use cosmic::iced_accessibility::A11yTree;
         A11yTree::join(
             self.children
                 .iter()
@@ -323,6 +327,16 @@ where
     }
 }
 
+/// Returns whether an event is passed on to the rows.
+///
+/// With accessibility, the focused row takes widget focus so that screen
+/// readers announce it, but keys are left to the always active search input.
+/// Otherwise a focused button would also act on Enter and Space.
+#[cfg(feature = "a11y")]
+fn forwards(event: &Event) -> bool {
+    !matches!(event, Event::Keyboard(_))
+}
+
 impl<'a, Message, Theme, Renderer> From<Column<'a, Message, Theme, Renderer>>
     for Element<'a, Message, Theme, Renderer>
 where
@@ -334,3 +348,18 @@ where
         Self::new(column)
     }
 }
+
+#[cfg(all(test, feature = "a11y"))]
+mod tests {
+    use super::*;
+    use cosmic::iced::core::keyboard::{self, Modifiers};
+
+    #[test]
+    fn rows_ignore_keys() {
+        let key = Event::Keyboard(keyboard::Event::ModifiersChanged(Modifiers::SHIFT));
+        let mouse = Event::Mouse(mouse::Event::CursorLeft);
+
+        assert!(!forwards(&key));
+        assert!(forwards(&mouse));
+    }
+}
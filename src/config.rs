@@ -1,3 +1,8 @@
+use cosmic::cosmic_config::{self, CosmicConfigEntry, cosmic_config_derive::CosmicConfigEntry};
+use crate::fallbacks::Fallback;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 pub fn profile() -> &'static str {
@@ -6,3 +11,56 @@ pub fn profile() -> &'static str {
         .nth_back(3)
         .unwrap_or("unknown")
 }
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, CosmicConfigEntry)]
+#[version = 1]
+pub struct Config {
+    /// GPU to always launch an app on, by desktop entry id and GPU name.
+    pub gpu_preferences: BTreeMap<String, String>,
+    /// Command line template that terminal apps and `t:` commands are
+    /// appended to, such as `foot` or `kitty --`.
+    pub terminal: Option<String>,
+    /// Namespaces of layers to keep clear of, even without an exclusive zone.
+    pub overlap_namespaces: Vec<String>,
+    /// Namespaces of layers never kept clear of, even with an exclusive zone.
+    pub ignored_namespaces: Vec<String>,
+    /// Whether to show a status bar with the result count, the active mode
+    /// and shortcut hints below the results.
+    pub footer: bool,
+    /// Actions offered when nothing matches the query, in order.
+    pub fallbacks: Vec<Fallback>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            gpu_preferences: BTreeMap::new(),
+            terminal: None,
+            overlap_namespaces: vec!["Dock".into(), "Panel".into()],
+            ignored_namespaces: Vec::new(),
+            footer: true,
+            fallbacks: Fallback::ALL.to_vec(),
+        }
+    }
+}
+
+impl Config {
+    /// Loads the launcher's config, falling back to defaults for invalid keys.
+    pub fn load(app_id: &str) -> (Option<cosmic_config::Config>, Self) {
+        let handler = match cosmic_config::Config::new(app_id, Self::VERSION) {
+            Ok(handler) => handler,
+            Err(why) => {
+                tracing::error!("failed to open config: {why}");
+                return (None, Self::default());
+            }
+        };
+
+        let config = Self::get_entry(&handler).unwrap_or_else(|(errors, config)| {
+            for why in errors.into_iter().filter(cosmic_config::Error::is_err) {
+                tracing::error!("failed to load config: {why}");
+            }
+            config
+        });
+        (Some(handler), config)
+    }
+}
@@ -0,0 +1,230 @@
+//! A dmenu-compatible picker that lists choices read from stdin.
+//!
+//! The choices are served by an in-process backend in place of pop-launcher,
+//! so the launcher's list, keyboard navigation and activation work unchanged.
+//! Like rofi, a line may carry an icon as `text\0icon\x1fname`.
+
+use crate::matching::fuzzy_score;
+use crate::subscriptions::launcher::{self, Backend, Client, Spawned};
+use cosmic::iced::Subscription;
+use futures::StreamExt;
+use futures::future::BoxFuture;
+use pop_launcher::{IconSource, Request, Response, SearchResult};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead};
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    pub text: String,
+    pub icon: Option<String>,
+}
+
+/// Choices and prompt of a dmenu invocation.
+#[derive(Debug, Clone)]
+pub struct Dmenu {
+    pub prompt: Option<String>,
+    entries: Arc<Vec<Entry>>,
+    /// The entry picked, set by the backend before it closes the launcher.
+    choice: Arc<Mutex<Option<String>>>,
+}
+
+impl Dmenu {
+    pub fn new(prompt: Option<String>, entries: Vec<Entry>) -> Self {
+        Self {
+            prompt,
+            entries: Arc::new(entries),
+            choice: Arc::default(),
+        }
+    }
+
+    /// Returns the text of the entry picked, if one was.
+    pub fn take_choice(&self) -> Option<String> {
+        self.choice.lock().unwrap().take()
+    }
+}
+
+// the entries never change, so identify them without hashing every line
+impl Hash for Dmenu {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        Arc::as_ptr(&self.entries).hash(state);
+    }
+}
+
+/// Reads one entry per line, parsing rofi's `\0key\x1fvalue` row options.
+pub fn read_entries(input: impl BufRead) -> io::Result<Vec<Entry>> {
+    input
+        .lines()
+        .filter(|line| !matches!(line, Ok(line) if line.is_empty()))
+        .map(|line| line.map(|line| parse_entry(&line)))
+        .collect()
+}
+
+fn parse_entry(line: &str) -> Entry {
+    let (text, options) = line.split_once('\0').unwrap_or((line, ""));
+    let mut options = options.split('\x1f');
+    let mut icon = None;
+    while let (Some(key), Some(value)) = (options.next(), options.next()) {
+        if key == "icon" && !value.is_empty() {
+            icon = Some(value.to_string());
+        }
+    }
+
+    Entry {
+        text: text.to_string(),
+        icon,
+    }
+}
+
+/// Returns the matching entries, best first, keeping the input order on ties.
+fn filter(entries: &[Entry], query: &str) -> Vec<SearchResult> {
+    let mut matches: Vec<_> = entries
+        .iter()
+        .enumerate()
+        .filter_map(|(i, entry)| Some((fuzzy_score(query, &entry.text)?, i, entry)))
+        .collect();
+    matches.sort_by(|a, b| b.0.cmp(&a.0));
+
+    matches
+        .into_iter()
+        .map(|(_, i, entry)| SearchResult {
+            #[allow(clippy::cast_possible_truncation)]
+            id: i as u32,
+            name: entry.text.clone(),
+            description: String::new(),
+            icon: entry.icon.clone().map(|icon| IconSource::Name(icon.into())),
+            category_icon: None,
+            window: None,
+        })
+        .collect()
+}
+
+pub fn subscription(dmenu: &Dmenu) -> Subscription<launcher::Event> {
+    Subscription::run_with(dmenu.clone(), |dmenu| {
+        let entries = dmenu.entries.clone();
+        let choice = dmenu.choice.clone();
+        launcher::restarting(move || {
+            launcher::service_with(DmenuBackend {
+                entries: entries.clone(),
+                choice: choice.clone(),
+            })
+        })
+    })
+}
+
+struct DmenuBackend {
+    entries: Arc<Vec<Entry>>,
+    choice: Arc<Mutex<Option<String>>>,
+}
+
+impl Backend for DmenuBackend {
+    fn spawn(&mut self) -> io::Result<Spawned> {
+        let (tx, rx) = mpsc::unbounded_channel();
+        let client = DmenuClient {
+            entries: self.entries.clone(),
+            choice: self.choice.clone(),
+            tx,
+        };
+        let responses = tokio_stream::wrappers::UnboundedReceiverStream::new(rx).boxed();
+        Ok((Box::new(client), responses))
+    }
+}
+
+struct DmenuClient {
+    entries: Arc<Vec<Entry>>,
+    choice: Arc<Mutex<Option<String>>>,
+    tx: mpsc::UnboundedSender<Response>,
+}
+
+impl Client for DmenuClient {
+    fn send(&mut self, request: Request) -> BoxFuture<'_, io::Result<()>> {
+        let response = match request {
+            Request::Search(query) => Some(Response::Update(filter(&self.entries, &query))),
+            Request::Complete(id) => self
+                .entries
+                .get(id as usize)
+                .map(|entry| Response::Fill(entry.text.clone())),
+            // the app quits on `Close`, and the choice is printed on the way out
+            Request::Activate(id) => self.entries.get(id as usize).map(|entry| {
+                *self.choice.lock().unwrap() = Some(entry.text.clone());
+                Response::Close
+            }),
+            Request::Close => Some(Response::Close),
+            _ => None,
+        };
+
+        if let Some(response) = response {
+            let _res = self.tx.send(response);
+        }
+        Box::pin(async { Ok(()) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_rofi_icons() {
+        let input =
+            b"Firefox\0icon\x1ffirefox\nplain\n\nmeta\0meta\x1fweb\x1ficon\x1fweb-browser\n";
+        let entries = read_entries(&input[..]).unwrap();
+
+        assert_eq!(
+            entries,
+            [
+                Entry {
+                    text: "Firefox".into(),
+                    icon: Some("firefox".into()),
+                },
+                Entry {
+                    text: "plain".into(),
+                    icon: None,
+                },
+                Entry {
+                    text: "meta".into(),
+                    icon: Some("web-browser".into()),
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn activating_records_the_choice_and_closes() {
+        let dmenu = Dmenu::new(None, read_entries(&b"gedit\nfirefox\n"[..]).unwrap());
+        let mut backend = DmenuBackend {
+            entries: dmenu.entries.clone(),
+            choice: dmenu.choice.clone(),
+        };
+        let (mut client, mut responses) = backend.spawn().unwrap();
+
+        client.send(Request::Activate(1)).await.unwrap();
+        assert!(matches!(responses.next().await, Some(Response::Close)));
+
+        assert_eq!(dmenu.take_choice().as_deref(), Some("firefox"));
+        assert_eq!(dmenu.take_choice(), None);
+    }
+
+    #[test]
+    fn filter_ranks_and_keeps_order() {
+        let entries: Vec<_> = ["gedit", "firefox", "files", "thunderbird"]
+            .into_iter()
+            .map(|text| Entry {
+                text: text.into(),
+                icon: None,
+            })
+            .collect();
+
+        let names = |query: &str| -> Vec<String> {
+            filter(&entries, query)
+                .into_iter()
+                .map(|result| result.name)
+                .collect()
+        };
+
+        assert_eq!(names(""), ["gedit", "firefox", "files", "thunderbird"]);
+        assert_eq!(names("fi"), ["firefox", "files"]);
+        assert_eq!(names("fx"), ["firefox"]);
+    }
+}
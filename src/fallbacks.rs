@@ -0,0 +1,157 @@
+//! Actions offered in place of results when nothing matches the query.
+//!
+//! Which actions are offered, and in what order, is set by the `fallbacks`
+//! config key. They are listed as regular rows, so they are focused and
+//! activated like results.
+
+use crate::fl;
+use crate::ids;
+use crate::open_with::push_quoted;
+use pop_launcher::{IconSource, SearchResult};
+use serde::{Deserialize, Serialize};
+
+/// Searched with the query appended, percent-encoded.
+const WEB_SEARCH: &str = "https://duckduckgo.com/?q=";
+
+/// Returns `true` if the result id was assigned to a fallback action.
+pub fn owns(id: u32) -> bool {
+    id & ids::FALLBACK != 0
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Fallback {
+    /// Search the web for the query.
+    Web,
+    /// Run the query as a shell command.
+    Shell,
+    /// Run the query as a shell command in a terminal.
+    Terminal,
+    /// Search for files named like the query.
+    Files,
+    /// Search the COSMIC Store for the query.
+    Store,
+}
+
+impl Fallback {
+    pub const ALL: [Self; 5] = [
+        Self::Web,
+        Self::Shell,
+        Self::Terminal,
+        Self::Files,
+        Self::Store,
+    ];
+
+    fn name(self, query: &str) -> String {
+        match self {
+            Self::Web => fl!("fallback-web", query = query),
+            Self::Shell => fl!("fallback-shell", query = query),
+            Self::Terminal => fl!("fallback-terminal", query = query),
+            Self::Files => fl!("fallback-files", query = query),
+            Self::Store => fl!("fallback-store", query = query),
+        }
+    }
+
+    fn icon(self) -> &'static str {
+        match self {
+            Self::Web => "web-browser",
+            Self::Shell => "system-run",
+            Self::Terminal => "utilities-terminal",
+            Self::Files => "system-file-manager",
+            Self::Store => "com.system76.CosmicStore",
+        }
+    }
+
+    /// Returns the command line that performs the action, for those that
+    /// launch a program without a terminal.
+    ///
+    /// The query, and the percent-encoded URL, are quoted with `%` escaped, as
+    /// the line is read for field codes when it is spawned.
+    pub fn exec(self, query: &str) -> Option<String> {
+        let mut exec = match self {
+            Self::Web => {
+                let mut exec = String::from("xdg-open ");
+                push_quoted(&mut exec, &[&format!("{WEB_SEARCH}{}", encode(query))]);
+                return Some(exec);
+            }
+            Self::Shell => String::from("sh -c "),
+            Self::Store => String::from("cosmic-store --search "),
+            Self::Terminal | Self::Files => return None,
+        };
+        push_quoted(&mut exec, &[query]);
+        Some(exec)
+    }
+}
+
+/// Lists the fallback actions for `query` as results.
+pub fn results(fallbacks: &[Fallback], query: &str) -> Vec<SearchResult> {
+    fallbacks
+        .iter()
+        .enumerate()
+        .map(|(i, fallback)| SearchResult {
+            #[allow(clippy::cast_possible_truncation)]
+            id: ids::FALLBACK | i as u32,
+            name: fallback.name(query),
+            description: String::new(),
+            icon: Some(IconSource::Name(fallback.icon().into())),
+            category_icon: None,
+            window: None,
+        })
+        .collect()
+}
+
+/// Returns the fallback action that a result id was assigned to.
+pub fn get(fallbacks: &[Fallback], id: u32) -> Option<Fallback> {
+    owns(id)
+        .then(|| fallbacks.get((id & !ids::FALLBACK) as usize))
+        .flatten()
+        .copied()
+}
+
+/// Percent-encodes a query for a URL, keeping only unreserved characters.
+fn encode(query: &str) -> String {
+    query.bytes().fold(String::new(), |mut encoded, byte| {
+        if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+            encoded.push(char::from(byte));
+        } else {
+            encoded.push_str(&format!("%{byte:02X}"));
+        }
+        encoded
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_map_back_to_fallbacks() {
+        let fallbacks = [Fallback::Files, Fallback::Web];
+        let results = results(&fallbacks, "notes");
+
+        assert!(results.iter().all(|result| owns(result.id)));
+        assert_eq!(get(&fallbacks, results[1].id), Some(Fallback::Web));
+        assert_eq!(get(&fallbacks, 1), None);
+        assert_eq!(get(&fallbacks, ids::FALLBACK | 2), None);
+    }
+
+    #[test]
+    fn quotes_the_query() {
+        assert_eq!(
+            Fallback::Web.exec("rust & C++").as_deref(),
+            Some(r#"xdg-open "https://duckduckgo.com/?q=rust%%20%%26%%20C%%2B%%2B""#)
+        );
+        assert_eq!(
+            Fallback::Shell.exec(r#"echo "$HOME""#).as_deref(),
+            Some(r#"sh -c "echo \"\$HOME\"""#)
+        );
+        assert_eq!(
+            Fallback::Shell.exec("printf 100%").as_deref(),
+            Some(r#"sh -c "printf 100%%""#)
+        );
+        assert_eq!(
+            Fallback::Store.exec("50%").as_deref(),
+            Some(r#"cosmic-store --search "50%%""#)
+        );
+        assert_eq!(Fallback::Files.exec("notes"), None);
+    }
+}
@@ -0,0 +1,26 @@
+//! Values shared by the tests of several modules.
+
+use crate::library::{App, Category};
+use pop_launcher::{IconSource, SearchResult};
+
+/// A result with only an id and a name, as pop-launcher lists an app.
+pub fn result(id: u32, name: &str) -> SearchResult {
+    SearchResult {
+        id,
+        name: name.into(),
+        description: String::new(),
+        icon: None,
+        category_icon: None,
+        window: None,
+    }
+}
+
+/// An installed app whose desktop entry does not exist.
+pub fn app(name: &str, category: Category) -> App {
+    App {
+        name: name.into(),
+        icon: IconSource::Name("application-default".into()),
+        path: format!("/nonexistent/{name}.desktop").into(),
+        category,
+    }
+}
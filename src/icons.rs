@@ -0,0 +1,216 @@
+//! Icons of results, resolved off the UI thread and cached by source.
+//!
+//! Looking an icon up in the theme, or checking that an icon path exists, reads
+//! the filesystem. Results are drawn with a placeholder until their icon is
+//! resolved in the background, and the cache is cleared when the icon theme
+//! changes, since a name may then resolve to another file. Once the cache
+//! grows past [`CAPACITY`], icons that are no longer shown are dropped.
+
+use cosmic::widget::icon::{self, IconFallback};
+use pop_launcher::IconSource;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Number of cached icons above which those not shown are dropped.
+const CAPACITY: usize = 256;
+
+/// Identifies an icon source in the cache.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Key {
+    Name(String),
+    Mime(String),
+}
+
+impl From<&IconSource> for Key {
+    fn from(source: &IconSource) -> Self {
+        match source {
+            IconSource::Name(name) => Self::Name(name.to_string()),
+            IconSource::Mime(mime) => Self::Mime(mime.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Icons {
+    handles: HashMap<Key, icon::Handle>,
+    pending: HashSet<Key>,
+    /// Bumped when the cache is cleared, so that icons resolved for the
+    /// previous theme are dropped.
+    generation: u64,
+    theme: Option<String>,
+}
+
+impl Icons {
+    pub fn get(&self, source: &IconSource) -> Option<&icon::Handle> {
+        self.handles.get(&Key::from(source))
+    }
+
+    /// Returns the shown sources that are neither cached nor being resolved,
+    /// with the generation to resolve them for, and marks them as being
+    /// resolved.
+    pub fn request<'a>(
+        &mut self,
+        shown: impl IntoIterator<Item = &'a IconSource>,
+    ) -> Option<(u64, Vec<Key>)> {
+        let shown: Vec<_> = shown.into_iter().map(Key::from).collect();
+        if self.handles.len() > CAPACITY {
+            let kept: HashSet<_> = shown.iter().collect();
+            self.handles.retain(|key, _| kept.contains(key));
+        }
+
+        let keys: Vec<_> = shown
+            .into_iter()
+            .filter(|key| !self.handles.contains_key(key) && self.pending.insert(key.clone()))
+            .collect();
+        (!keys.is_empty()).then_some((self.generation, keys))
+    }
+
+    /// Caches icons resolved for `generation`, and stops waiting on the
+    /// `requested` ones, so those that failed are requested again.
+    pub fn insert(
+        &mut self,
+        generation: u64,
+        requested: &[Key],
+        resolved: Vec<(Key, icon::Handle)>,
+    ) {
+        if generation != self.generation {
+            return;
+        }
+        for key in requested {
+            self.pending.remove(key);
+        }
+        self.handles.extend(resolved);
+    }
+
+    /// Records the active icon theme, clearing the cache if it changed.
+    ///
+    /// Returns `true` if the cache was cleared.
+    pub fn set_theme(&mut self, theme: String) -> bool {
+        if self.theme.as_ref() == Some(&theme) {
+            return false;
+        }
+        let changed = self.theme.replace(theme).is_some();
+        if changed {
+            self.handles.clear();
+            self.pending.clear();
+            self.generation += 1;
+        }
+        changed
+    }
+}
+
+fn named(name: &str, fallback: &[&str]) -> icon::Named {
+    icon::from_name(name)
+        .prefer_svg(true)
+        .size(64)
+        .fallback(Some(IconFallback::Names(
+            fallback
+                .iter()
+                .map(|&name| name.to_string().into())
+                .collect(),
+        )))
+}
+
+/// Resolves a named icon to its file, leaving the lookup to the view if the
+/// theme has no such icon.
+fn lookup(named: icon::Named) -> icon::Handle {
+    match named.clone().path() {
+        Some(path) => icon::from_path(path),
+        None => named.handle(),
+    }
+}
+
+/// Resolves icons to their files, reading the filesystem.
+pub fn resolve(keys: Vec<Key>) -> Vec<(Key, icon::Handle)> {
+    keys.into_iter()
+        .map(|key| {
+            let handle = match &key {
+                // the name may be the path of an icon file
+                Key::Name(name) if name.contains('/') => {
+                    let path = Path::new(name);
+                    if path.exists() {
+                        icon::from_path(path.into())
+                    } else {
+                        lookup(named("application-default", &["application-x-executable"]))
+                    }
+                }
+                Key::Name(name) => lookup(named(
+                    name,
+                    &["application-default", "application-x-executable"],
+                )),
+                Key::Mime(mime) => lookup(named(
+                    &mime.replace('/', "-"),
+                    &["application-default", "application-x-executable"],
+                )),
+            };
+            (key, handle)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn handle() -> icon::Handle {
+        icon::from_name("firefox").handle()
+    }
+
+    #[test]
+    fn requests_each_source_once() {
+        let mut icons = Icons::default();
+        let firefox = IconSource::Name("firefox".into());
+        let text = IconSource::Mime("text/plain".into());
+
+        let (generation, keys) = icons.request([&firefox, &text, &firefox]).unwrap();
+        assert_eq!(
+            keys,
+            [Key::Name("firefox".into()), Key::Mime("text/plain".into())]
+        );
+        assert!(icons.request([&firefox]).is_none());
+        assert!(icons.get(&firefox).is_none());
+
+        icons.insert(generation, &keys, vec![(Key::from(&firefox), handle())]);
+        assert!(icons.get(&firefox).is_some());
+        // resolving the text icon failed
+        assert_eq!(
+            icons.request([&firefox, &text]).map(|(_, keys)| keys),
+            Some(vec![Key::from(&text)])
+        );
+    }
+
+    #[test]
+    fn drops_icons_no_longer_shown() {
+        let mut icons = Icons::default();
+        let sources: Vec<_> = (0..=CAPACITY)
+            .map(|i| IconSource::Name(format!("app-{i}").into()))
+            .collect();
+        let (generation, keys) = icons.request(&sources).unwrap();
+        let resolved = keys.iter().map(|key| (key.clone(), handle())).collect();
+        icons.insert(generation, &keys, resolved);
+        assert!(sources.iter().all(|source| icons.get(source).is_some()));
+
+        assert!(icons.request(&sources[..1]).is_none());
+        assert!(icons.get(&sources[0]).is_some());
+        assert!(icons.get(&sources[1]).is_none());
+    }
+
+    #[test]
+    fn theme_change_drops_stale_icons() {
+        let mut icons = Icons::default();
+        let firefox = IconSource::Name("firefox".into());
+
+        assert!(!icons.set_theme("Cosmic".into()));
+        let (generation, keys) = icons.request([&firefox]).unwrap();
+        assert!(!icons.set_theme("Cosmic".into()));
+        assert!(icons.set_theme("Pop".into()));
+
+        // resolved for the previous theme
+        icons.insert(generation, &keys, vec![(Key::from(&firefox), handle())]);
+        assert!(icons.get(&firefox).is_none());
+
+        let (generation, keys) = icons.request([&firefox]).unwrap();
+        icons.insert(generation, &keys, vec![(Key::from(&firefox), handle())]);
+        assert!(icons.get(&firefox).is_some());
+    }
+}
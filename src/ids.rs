@@ -0,0 +1,25 @@
+//! Result ids of the sources merged with the pop-launcher results.
+//!
+//! pop-launcher numbers its results from zero, so every other source marks
+//! its ids with a high bit of its own, and requests for a result are routed
+//! back to the source that listed it.
+
+/// Results of the in-process providers.
+pub const PROVIDER: u32 = 1 << 31;
+/// Results of the user's scripts.
+pub const SCRIPT: u32 = 1 << 30;
+/// Fallback actions, listed when nothing matches.
+pub const FALLBACK: u32 = 1 << 29;
+/// Bits below every source bit, left for the ids within a source.
+pub const LOCAL: u32 = FALLBACK - 1;
+
+// each source has a single bit that no other source, nor any local id, uses
+const _: () = assert!(
+    PROVIDER.is_power_of_two()
+        && SCRIPT.is_power_of_two()
+        && FALLBACK.is_power_of_two()
+        && PROVIDER & SCRIPT == 0
+        && PROVIDER & FALLBACK == 0
+        && SCRIPT & FALLBACK == 0
+        && (PROVIDER | SCRIPT | FALLBACK) & LOCAL == 0
+);
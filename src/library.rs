@@ -0,0 +1,159 @@
+//! The app library: a grid of every installed app, grouped by category.
+
+use crate::fl;
+use crate::matching::fuzzy_score;
+use cosmic::desktop::fde;
+use pop_launcher::IconSource;
+use std::path::PathBuf;
+
+/// Groups of the main categories of the desktop menu specification, in the
+/// order they are shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    AudioVideo,
+    Development,
+    Education,
+    Game,
+    Graphics,
+    Network,
+    Office,
+    Science,
+    Settings,
+    System,
+    Utility,
+    Other,
+}
+
+impl Category {
+    /// Returns the group of the first main category in `categories`.
+    pub fn of(categories: &[impl AsRef<str>]) -> Self {
+        categories
+            .iter()
+            .find_map(|category| {
+                Some(match category.as_ref() {
+                    "AudioVideo" | "Audio" | "Video" => Self::AudioVideo,
+                    "Development" => Self::Development,
+                    "Education" => Self::Education,
+                    "Game" => Self::Game,
+                    "Graphics" => Self::Graphics,
+                    "Network" => Self::Network,
+                    "Office" => Self::Office,
+                    "Science" => Self::Science,
+                    "Settings" => Self::Settings,
+                    "System" => Self::System,
+                    "Utility" => Self::Utility,
+                    _ => return None,
+                })
+            })
+            .unwrap_or(Self::Other)
+    }
+
+    pub fn name(self) -> String {
+        match self {
+            Self::AudioVideo => fl!("category-audio-video"),
+            Self::Development => fl!("category-development"),
+            Self::Education => fl!("category-education"),
+            Self::Game => fl!("category-game"),
+            Self::Graphics => fl!("category-graphics"),
+            Self::Network => fl!("category-network"),
+            Self::Office => fl!("category-office"),
+            Self::Science => fl!("category-science"),
+            Self::Settings => fl!("category-settings"),
+            Self::System => fl!("category-system"),
+            Self::Utility => fl!("category-utility"),
+            Self::Other => fl!("category-other"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct App {
+    pub name: String,
+    pub icon: IconSource,
+    pub path: PathBuf,
+    pub category: Category,
+}
+
+/// Loads the installed apps that are shown in menus, sorted by name.
+///
+/// Reads every desktop entry, so it must not run on the UI thread.
+pub fn load() -> Vec<App> {
+    let locales = fde::get_languages_from_env();
+    let mut apps: Vec<_> = cosmic::desktop::load_applications(&locales, false, None)
+        .into_iter()
+        .filter(|entry| entry.exec.is_some())
+        .filter_map(|entry| {
+            let icon = match entry.icon {
+                cosmic::desktop::IconSource::Name(name) => name,
+                cosmic::desktop::IconSource::Path(path) => path.to_string_lossy().into_owned(),
+            };
+            Some(App {
+                category: Category::of(&entry.categories),
+                name: entry.name,
+                icon: IconSource::Name(icon.into()),
+                path: entry.path?,
+            })
+        })
+        .collect();
+    apps.sort_by_cached_key(|app| app.name.to_lowercase());
+    apps
+}
+
+/// Returns the indices of the apps matching `query`, grouped by category.
+pub fn groups(apps: &[App], query: &str) -> Vec<(Category, Vec<usize>)> {
+    let query = query.trim();
+    let mut groups: Vec<(Category, Vec<usize>)> = Vec::new();
+    for (i, app) in apps.iter().enumerate() {
+        if fuzzy_score(query, &app.name).is_none() {
+            continue;
+        }
+        match groups
+            .iter_mut()
+            .find(|(category, _)| *category == app.category)
+        {
+            Some((_, apps)) => apps.push(i),
+            None => groups.push((app.category, vec![i])),
+        }
+    }
+    groups.sort_by_key(|(category, _)| *category);
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::app;
+
+    #[test]
+    fn groups_by_main_category() {
+        assert_eq!(
+            Category::of(&["GTK", "Video", "Player"]),
+            Category::AudioVideo
+        );
+        assert_eq!(
+            Category::of(&["Qt", "Network", "Office"]),
+            Category::Network
+        );
+        assert_eq!(Category::of::<&str>(&[]), Category::Other);
+
+        let apps = [
+            app("Files", Category::System),
+            app("Firefox", Category::Network),
+            app("Terminal", Category::System),
+            app("Text Editor", Category::Utility),
+        ];
+
+        assert_eq!(
+            groups(&apps, ""),
+            [
+                (Category::Network, vec![1]),
+                (Category::System, vec![0, 2]),
+                (Category::Utility, vec![3]),
+            ]
+        );
+        assert_eq!(
+            groups(&apps, "te"),
+            [(Category::System, vec![2]), (Category::Utility, vec![3])]
+        );
+    }
+}
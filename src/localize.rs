@@ -2,6 +2,7 @@
 // SPDX-License-Identifier: GPL-3.0-only
 
 use i18n_embed::fluent::{FluentLanguageLoader, fluent_language_loader};
+use i18n_embed::unic_langid::LanguageIdentifier;
 use i18n_embed::{DefaultLocalizer, LanguageLoader, Localizer};
 use rust_embed::RustEmbed;
 use std::sync::LazyLock;
@@ -31,6 +32,19 @@ macro_rules! fl {
     }};
 }
 
+/// Languages written right to left, for which the layout is mirrored.
+const RTL_LANGUAGES: &[&str] = &["ar", "fa", "he", "ur"];
+
+/// Returns `true` if `language` is written right to left.
+pub fn is_rtl(language: &LanguageIdentifier) -> bool {
+    RTL_LANGUAGES.contains(&language.language.as_str())
+}
+
+/// Returns `true` if the active language is written right to left.
+pub fn rtl() -> bool {
+    is_rtl(&LANGUAGE_LOADER.current_language())
+}
+
 // Get the `Localizer` to be used for localizing this library.
 pub fn localizer() -> Box<dyn Localizer> {
     Box::from(DefaultLocalizer::new(&*LANGUAGE_LOADER, &Localizations))
@@ -44,3 +58,19 @@ pub fn localize() {
         eprintln!("Error while loading language for App List {error}");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_rtl_languages() {
+        let rtl = |id: &str| is_rtl(&id.parse().unwrap());
+
+        assert!(rtl("ar"));
+        assert!(rtl("he-IL"));
+        assert!(rtl("fa"));
+        assert!(!rtl("en-GB"));
+        assert!(!rtl("fr"));
+    }
+}
@@ -2,15 +2,29 @@ mod components;
 #[rustfmt::skip]
 mod config;
 mod app;
+mod dmenu;
+mod fallbacks;
+#[cfg(test)]
+mod fixtures;
+mod icons;
+mod ids;
+mod library;
 mod localize;
+mod matching;
+mod open_with;
+mod overlap;
+mod query;
+mod sizing;
 mod subscriptions;
+mod terminal;
 use tracing::info;
 
 use localize::localize;
 
 use crate::config::VERSION;
+use std::process::ExitCode;
 
-fn main() -> cosmic::iced::Result {
+fn main() -> Result<ExitCode, cosmic::iced::Error> {
     init_logging();
 
     info!(
@@ -0,0 +1,38 @@
+//! Fuzzy matching of queries, shared by the dmenu picker and the app library.
+
+/// Scores `text` against `pattern` as a case-insensitive subsequence match,
+/// favouring consecutive characters and matches at the start of words.
+pub fn fuzzy_score(pattern: &str, text: &str) -> Option<u32> {
+    let text: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut last = None;
+
+    for p in pattern.chars().flat_map(char::to_lowercase) {
+        let i = pos + text[pos..].iter().position(|&c| c == p)?;
+        score += 1;
+        if last.is_some_and(|last: usize| last + 1 == i) {
+            score += 4;
+        }
+        if i == 0 || !text[i - 1].is_alphanumeric() {
+            score += 2;
+        }
+        last = Some(i);
+        pos = i + 1;
+    }
+
+    Some(score)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fuzzy_matches_subsequences() {
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+        assert!(fuzzy_score("ffx", "Firefox").is_some());
+        assert!(fuzzy_score("xff", "Firefox").is_none());
+        assert!(fuzzy_score("fire", "Firefox") > fuzzy_score("fire", "Safe Fi Re"));
+    }
+}
@@ -0,0 +1,320 @@
+//! Opening files with an app, by typing `<app query> > <file>...`.
+//!
+//! Once the separator is typed, the app listed that best matches the text
+//! before it is kept, and the results list completions for the file argument
+//! being typed. The chosen files or URIs are substituted into the `%f`, `%F`,
+//! `%u` and `%U` field codes of the app's `Exec` line when it is launched, or
+//! appended to it if it has none.
+
+use std::path::PathBuf;
+
+/// Separates the app query from the files, matched as soon as `>` is typed so
+/// that the app is picked from the results of the query before it.
+pub const SEPARATOR: &str = " >";
+/// Maximum number of completions listed for a directory.
+const MAX_COMPLETIONS: usize = 50;
+
+/// A file or directory that completes the argument being typed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion {
+    /// Name shown in the results, with a trailing `/` for directories.
+    pub name: String,
+    /// The completed argument.
+    pub arg: String,
+    pub dir: bool,
+}
+
+/// Splits an input into the app query and its file arguments.
+///
+/// Queries for plugins picked by a prefix, such as `t:`, `:` or `=`, are not
+/// split, as `>` is a redirection or a comparison there.
+pub fn split(input: &str) -> Option<(&str, &str)> {
+    let (query, args) = input.split_once(SEPARATOR)?;
+    let query = query.trim_start();
+    let first_word = query.split_whitespace().next()?;
+    if !query.starts_with(char::is_alphanumeric) || first_word.contains(':') {
+        return None;
+    }
+    Some((query, args))
+}
+
+/// Splits file arguments on whitespace, except where quoted with `"` or
+/// escaped with `\`.
+///
+/// The last argument is the one being typed, which is empty after a space.
+pub fn parse_args(args: &str) -> Vec<String> {
+    let mut parsed = vec![String::new()];
+    let mut quoted = false;
+    let mut chars = args.chars();
+    while let Some(c) = chars.next() {
+        let last = parsed.last_mut().unwrap();
+        match c {
+            '\\' => last.extend(chars.next()),
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !last.is_empty() {
+                    parsed.push(String::new());
+                }
+            }
+            c => last.push(c),
+        }
+    }
+    parsed
+}
+
+/// Joins an app query and file arguments into an input, the inverse of
+/// [`split`] and [`parse_args`].
+pub fn join(query: &str, args: &[String]) -> String {
+    let args: Vec<_> = args
+        .iter()
+        .map(|arg| {
+            arg.chars().fold(String::new(), |mut escaped, c| {
+                if c.is_whitespace() || c == '\\' || c == '"' {
+                    escaped.push('\\');
+                }
+                escaped.push(c);
+                escaped
+            })
+        })
+        .collect();
+    format!("{query}{SEPARATOR} {}", args.join(" "))
+}
+
+fn is_uri(arg: &str) -> bool {
+    arg.contains("://")
+}
+
+fn home() -> PathBuf {
+    std::env::var_os("HOME").map_or_else(|| PathBuf::from("/"), PathBuf::from)
+}
+
+/// Resolves an argument to an absolute path, relative to the home directory,
+/// leaving URIs as they are.
+pub fn resolve(arg: &str) -> String {
+    if is_uri(arg) || arg.starts_with('/') {
+        return arg.to_string();
+    }
+    let relative = arg
+        .strip_prefix('~')
+        .map_or(arg, |rest| rest.trim_start_matches('/'));
+    home().join(relative).to_string_lossy().into_owned()
+}
+
+/// Lists the directory entries that complete `arg`, directories first.
+///
+/// Hidden entries are only listed once the name being typed starts with `.`.
+pub fn completions(arg: &str) -> Vec<Completion> {
+    if is_uri(arg) {
+        return Vec::new();
+    }
+
+    let (parent, prefix) = match arg.rfind('/') {
+        Some(i) => arg.split_at(i + 1),
+        None => ("", arg),
+    };
+    let Ok(entries) = std::fs::read_dir(resolve(parent)) else {
+        return Vec::new();
+    };
+
+    let prefix = prefix.to_lowercase();
+    let mut completions: Vec<_> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.to_lowercase().starts_with(&prefix)
+                || (name.starts_with('.') && !prefix.starts_with('.'))
+            {
+                return None;
+            }
+            // follows symlinks, so that links to directories can be entered
+            let dir = entry.path().is_dir();
+            let name = if dir { format!("{name}/") } else { name };
+            Some(Completion {
+                arg: format!("{parent}{name}"),
+                name,
+                dir,
+            })
+        })
+        .collect();
+
+    completions.sort_by(|a, b| b.dir.cmp(&a.dir).then_with(|| a.name.cmp(&b.name)));
+    completions.truncate(MAX_COMPLETIONS);
+    completions
+}
+
+/// Substitutes resolved files or URIs into the field codes of an `Exec` line.
+///
+/// `%f` and `%F` take local paths, so `file://` URIs are turned into paths and
+/// other URIs are left out. `%u` and `%U` take both. A line without any of them
+/// is given the files as arguments, with `file://` URIs turned into paths.
+///
+/// The line is read for field codes again when it is spawned, so `%%` is kept
+/// and a `%` in a file is escaped by [`push_quoted`].
+pub fn substitute(exec: &str, files: &[String]) -> String {
+    let local = |file: &String| file.strip_prefix("file://").map(decode);
+    let paths: Vec<_> = files
+        .iter()
+        .filter_map(|file| match local(file) {
+            Some(path) => Some(path),
+            None if is_uri(file) => None,
+            None => Some(file.clone()),
+        })
+        .collect();
+    let args: Vec<_> = files
+        .iter()
+        .map(|file| local(file).unwrap_or_else(|| file.clone()))
+        .collect();
+    let paths: Vec<_> = paths.iter().map(String::as_str).collect();
+    let uris: Vec<_> = files.iter().map(String::as_str).collect();
+    let args: Vec<_> = args.iter().map(String::as_str).collect();
+
+    let mut substituted = String::with_capacity(exec.len());
+    let mut has_files = false;
+    let mut chars = exec.chars();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            substituted.push(c);
+            continue;
+        }
+        let code = chars.next();
+        has_files |= matches!(code, Some('f' | 'F' | 'u' | 'U'));
+        match code {
+            Some('f') => push_quoted(&mut substituted, &paths[..paths.len().min(1)]),
+            Some('F') => push_quoted(&mut substituted, &paths),
+            Some('u') => push_quoted(&mut substituted, &uris[..uris.len().min(1)]),
+            Some('U') => push_quoted(&mut substituted, &uris),
+            Some('%') => substituted.push_str("%%"),
+            // other field codes are left to the launcher
+            Some(c) => {
+                substituted.push('%');
+                substituted.push(c);
+            }
+            None => substituted.push('%'),
+        }
+    }
+
+    if !has_files && !args.is_empty() {
+        substituted.push(' ');
+        push_quoted(&mut substituted, &args);
+    }
+    substituted
+}
+
+/// Percent-decodes the path of a `file://` URI, keeping invalid escapes.
+fn decode(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| bytes.get(i + 1..i + 3))
+            .flatten()
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Appends arguments quoted as described in the desktop entry specification.
+///
+/// The line is read for field codes when it is spawned, so `%` is escaped as
+/// `%%`.
+pub(crate) fn push_quoted(exec: &mut String, args: &[&str]) {
+    for (i, arg) in args.iter().enumerate() {
+        if i > 0 {
+            exec.push(' ');
+        }
+        exec.push('"');
+        for c in arg.chars() {
+            match c {
+                '"' | '`' | '$' | '\\' => exec.push('\\'),
+                '%' => exec.push('%'),
+                _ => {}
+            }
+            exec.push(c);
+        }
+        exec.push('"');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_quoted_and_escaped_args() {
+        assert_eq!(parse_args(""), [""]);
+        assert_eq!(parse_args("a.txt "), ["a.txt", ""]);
+        assert_eq!(
+            parse_args(r#""My Files/a b.txt" c\ d.txt e"#),
+            ["My Files/a b.txt", "c d.txt", "e"]
+        );
+
+        let args = vec!["My Files/".to_string(), r#"say "hi".txt"#.to_string()];
+        let input = join("gimp", &args);
+        assert_eq!(input, r#"gimp > My\ Files/ say\ \"hi\".txt"#);
+        assert_eq!(split(&input).map(|(_, args)| parse_args(args)), Some(args));
+    }
+
+    #[test]
+    fn leaves_prefixed_queries_unsplit() {
+        assert_eq!(split("gimp > a.png"), Some(("gimp", " a.png")));
+        assert_eq!(split("t:make > log"), None);
+        assert_eq!(split(": cmd > out"), None);
+        assert_eq!(split("= 3 > 2"), None);
+        assert_eq!(split(" > a.png"), None);
+    }
+
+    #[test]
+    fn substitutes_field_codes() {
+        let files = [
+            "/home/me/a b.png".to_string(),
+            "file:///tmp/c.png".to_string(),
+            "https://example.com/$d.png".to_string(),
+        ];
+
+        assert_eq!(
+            substitute("gimp-2.10 %U", &files),
+            r#"gimp-2.10 "/home/me/a b.png" "file:///tmp/c.png" "https://example.com/\$d.png""#
+        );
+        assert_eq!(
+            substitute("eog %F", &files),
+            r#"eog "/home/me/a b.png" "/tmp/c.png""#
+        );
+        assert_eq!(
+            substitute("viewer --single %f --icon %i 100%%", &files),
+            r#"viewer --single "/home/me/a b.png" --icon %i 100%%"#
+        );
+        assert_eq!(
+            substitute("eog %f", &["/home/me/100%f.png".to_string()]),
+            r#"eog "/home/me/100%%f.png""#
+        );
+        assert_eq!(
+            substitute("nofiles", &files),
+            r#"nofiles "/home/me/a b.png" "/tmp/c.png" "https://example.com/\$d.png""#
+        );
+        assert_eq!(substitute("nofiles", &[]), "nofiles");
+    }
+
+    #[test]
+    fn decodes_file_uris() {
+        let files = ["file:///tmp/a%20b%25.png".to_string()];
+
+        assert_eq!(substitute("eog %f", &files), r#"eog "/tmp/a b%%.png""#);
+        assert_eq!(
+            substitute("eog %u", &files),
+            r#"eog "file:///tmp/a%%20b%%25.png""#
+        );
+        assert_eq!(decode("/tmp/100%.png"), "/tmp/100%.png");
+    }
+}
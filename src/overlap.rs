@@ -0,0 +1,122 @@
+//! Keeping the launcher clear of docks and panels on every edge of the output.
+//!
+//! Layers overlapping the output are reported with their namespace and
+//! exclusive zone. Those that reserve space, or whose namespace is listed in the
+//! config, push the launcher away from the edge they are attached to, and the
+//! launcher is centered in the area left over.
+
+use crate::config::Config;
+use cosmic::iced::{Rectangle, Size};
+
+/// A layer surface reported to overlap the output.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Layer {
+    pub namespace: String,
+    pub exclusive: u32,
+    /// Position and size in output coordinates.
+    pub rect: Rectangle,
+}
+
+impl Layer {
+    /// Whether the launcher should be kept clear of this layer.
+    pub fn avoided(&self, config: &Config) -> bool {
+        if config.ignored_namespaces.contains(&self.namespace) {
+            return false;
+        }
+        self.exclusive > 0 || config.overlap_namespaces.contains(&self.namespace)
+    }
+}
+
+/// Space taken from each edge of the output, in logical pixels.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Edges {
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub left: f32,
+}
+
+impl Edges {
+    /// Horizontal offset that centers the launcher in the free area, positive
+    /// to the right.
+    pub fn center_offset(&self) -> f32 {
+        (self.left - self.right) / 2.
+    }
+}
+
+/// Computes the space that layers take from each edge of an output.
+///
+/// A layer wider than it is tall is attached to the top or bottom edge, and
+/// otherwise to the left or right edge, whichever its center is closer to.
+pub fn edges<'a>(output: Size, layers: impl IntoIterator<Item = &'a Rectangle>) -> Edges {
+    let mut edges = Edges::default();
+    for rect in layers {
+        let center = rect.center();
+        if rect.width >= rect.height {
+            if center.y < output.height / 2. {
+                edges.top = edges.top.max(rect.y + rect.height);
+            } else {
+                edges.bottom = edges.bottom.max(output.height - rect.y);
+            }
+        } else if center.x < output.width / 2. {
+            edges.left = edges.left.max(rect.x + rect.width);
+        } else {
+            edges.right = edges.right.max(output.width - rect.x);
+        }
+    }
+    edges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OUTPUT: Size = Size::new(1920., 1080.);
+
+    fn rect(x: f32, y: f32, width: f32, height: f32) -> Rectangle {
+        Rectangle {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    #[test]
+    fn takes_space_from_every_edge() {
+        let panel = rect(0., 0., 1920., 32.);
+        let dock = rect(600., 1000., 720., 80.);
+        let left = rect(0., 32., 64., 1048.);
+        let right = rect(1840., 100., 80., 600.);
+
+        assert_eq!(
+            edges(OUTPUT, [&panel, &dock, &left, &right]),
+            Edges {
+                top: 32.,
+                right: 80.,
+                bottom: 80.,
+                left: 64.,
+            }
+        );
+        assert_eq!(edges(OUTPUT, [&left]).center_offset(), 32.);
+        assert_eq!(edges(OUTPUT, []), Edges::default());
+    }
+
+    #[test]
+    fn avoids_configured_namespaces() {
+        let config = Config {
+            ignored_namespaces: vec!["wallpaper-clock".into()],
+            ..Config::default()
+        };
+        let layer = |namespace: &str, exclusive| Layer {
+            namespace: namespace.into(),
+            exclusive,
+            rect: rect(0., 0., 1920., 32.),
+        };
+
+        assert!(layer("Panel", 0).avoided(&config));
+        assert!(layer("my-bar", 32).avoided(&config));
+        assert!(!layer("my-bar", 0).avoided(&config));
+        assert!(!layer("wallpaper-clock", 32).avoided(&config));
+    }
+}
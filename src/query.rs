@@ -0,0 +1,135 @@
+//! Headless searches through pop-launcher, for shell scripts and for
+//! reproducing ranking issues without opening the launcher.
+
+use crate::app::{CosmicLauncher, desktop_exec, launch};
+use crate::config::Config;
+use crate::subscriptions::gpus;
+use crate::subscriptions::launcher::{self, Event, Request, ServiceState};
+use crate::terminal;
+use cosmic::Application;
+use futures::StreamExt;
+use pop_launcher::{IconSource, Response, SearchResult};
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::io::Write;
+use std::process::ExitCode;
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, clap::ValueEnum)]
+pub enum Format {
+    /// A JSON array of results
+    Json,
+    /// One tab-separated `index name description` line per result
+    Plain,
+}
+
+#[derive(Serialize)]
+struct QueryResult<'a> {
+    index: usize,
+    id: u32,
+    name: &'a str,
+    description: &'a str,
+    icon: Option<&'a IconSource>,
+    window: Option<(u32, u32)>,
+}
+
+/// Prints the results for `query` in the order returned by pop-launcher, and
+/// optionally activates one of them.
+pub fn run(query: String, format: Format, activate: Option<usize>) -> ExitCode {
+    let result = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .map_err(Box::<dyn Error>::from)
+        .and_then(|rt| rt.block_on(search(query, format, activate)));
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(why) => {
+            eprintln!("cosmic-launcher: {why}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn search(
+    query: String,
+    format: Format,
+    activate: Option<usize>,
+) -> Result<(), Box<dyn Error>> {
+    let mut events = std::pin::pin!(launcher::service());
+    let mut tx = None;
+    let mut printed = false;
+
+    while let Some(event) = events.next().await {
+        match event {
+            Event::Started(sender) => {
+                sender.send(Request::Search(query.clone())).await?;
+                tx = Some(sender);
+            }
+            Event::State(ServiceState::GaveUp) | Event::ServiceIsClosed => {
+                return Err("pop-launcher is unavailable".into());
+            }
+            Event::State(_) => {}
+            Event::Response(Response::Update(list)) if !printed => {
+                printed = true;
+                print(&list, format)?;
+
+                let Some(n) = activate else {
+                    return Ok(());
+                };
+                let item = list
+                    .get(n)
+                    .ok_or_else(|| format!("no result at index {n}"))?;
+                if let Some(tx) = &tx {
+                    tx.send(Request::Activate(item.id)).await?;
+                }
+            }
+            Event::Response(Response::DesktopEntry {
+                path,
+                gpu_preference,
+                action_name,
+            }) => {
+                let (app_id, exec, terminal) = desktop_exec(path, action_name)?;
+                let (_, config) = Config::load(CosmicLauncher::APP_ID);
+                let (exec, terminal) =
+                    terminal::command(config.terminal.as_deref(), exec, terminal);
+                let gpus = gpus::system().await.unwrap_or_default();
+                let gpu_envs = gpus::environment(&gpus, gpu_preference);
+                launch(None, app_id, exec, gpu_envs, terminal).await;
+                return Ok(());
+            }
+            Event::Response(Response::Fill(text)) => {
+                println!("{text}");
+                return Ok(());
+            }
+            Event::Response(Response::Close) => return Ok(()),
+            Event::Response(_) => {}
+        }
+    }
+
+    Err("pop-launcher service ended".into())
+}
+
+fn print(list: &[SearchResult], format: Format) -> std::io::Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    match format {
+        Format::Json => {
+            let results: Vec<_> = list
+                .iter()
+                .enumerate()
+                .map(|(index, item)| QueryResult {
+                    index,
+                    id: item.id,
+                    name: &item.name,
+                    description: &item.description,
+                    icon: item.icon.as_ref(),
+                    window: item.window,
+                })
+                .collect();
+            serde_json::to_writer(&mut stdout, &results)?;
+            writeln!(stdout)
+        }
+        Format::Plain => list.iter().enumerate().try_for_each(|(index, item)| {
+            writeln!(stdout, "{index}\t{}\t{}", item.name, item.description)
+        }),
+    }
+}
@@ -0,0 +1,108 @@
+//! Sizing the launcher in proportion to the output it opens on.
+
+use crate::overlap::Edges;
+use cosmic::cctk::sctk::output::OutputInfo;
+use cosmic::cctk::wayland_client::protocol::wl_output::Transform;
+use cosmic::iced::Size;
+
+/// Share of the free output width taken by the launcher.
+const WIDTH_RATIO: f32 = 1. / 3.;
+const MIN_WIDTH: f32 = 600.;
+const MAX_WIDTH: f32 = 960.;
+/// Share of the free output height taken by the results list.
+const LIST_HEIGHT_RATIO: f32 = 0.5;
+const MIN_LIST_HEIGHT: f32 = 240.;
+const MAX_LIST_HEIGHT: f32 = 840.;
+/// Height of everything around the results list: the search input, the
+/// padding of the launcher, and the gap above it.
+const CHROME_HEIGHT: f32 = 140.;
+/// Width of the launcher's padding and the gap kept on both sides.
+const CHROME_WIDTH: f32 = 96.;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sizing {
+    /// Width of the search input and results.
+    pub width: f32,
+    /// Height the results list scrolls at.
+    pub list_height: f32,
+}
+
+impl Default for Sizing {
+    fn default() -> Self {
+        Self {
+            width: 600.,
+            list_height: 504.,
+        }
+    }
+}
+
+impl Sizing {
+    /// Sizes the launcher for the area of `output` not taken by `edges`,
+    /// shrinking it below the minimums only if it would not fit.
+    pub fn new(output: Size, edges: Edges) -> Self {
+        let free_width = output.width - edges.left - edges.right - CHROME_WIDTH;
+        let free_height = output.height - edges.top - edges.bottom - CHROME_HEIGHT;
+        Self {
+            width: (free_width * WIDTH_RATIO)
+                .clamp(MIN_WIDTH, MAX_WIDTH)
+                .min(free_width)
+                .max(1.),
+            list_height: (free_height * LIST_HEIGHT_RATIO)
+                .clamp(MIN_LIST_HEIGHT, MAX_LIST_HEIGHT)
+                .min(free_height)
+                .max(1.),
+        }
+    }
+}
+
+/// Returns the logical size of an output, computing it from the current mode
+/// and scale if the compositor did not report it.
+#[allow(clippy::cast_precision_loss)]
+pub fn logical_size(info: &OutputInfo) -> Option<Size> {
+    if let Some((width, height)) = info.logical_size {
+        return Some(Size::new(width as f32, height as f32));
+    }
+    let mode = info.modes.iter().find(|mode| mode.current)?;
+    let (width, height) = mode.dimensions;
+    let scale = info.scale_factor.max(1) as f32;
+    let (width, height) = match info.transform {
+        Transform::_90 | Transform::_270 | Transform::Flipped90 | Transform::Flipped270 => {
+            (height, width)
+        }
+        _ => (width, height),
+    };
+    Some(Size::new(width as f32 / scale, height as f32 / scale))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scales_with_the_output() {
+        let laptop = Sizing::new(Size::new(1366., 768.), Edges::default());
+        assert_eq!(laptop.width, 600.);
+        assert_eq!(laptop.list_height, 314.);
+
+        let uhd = Sizing::new(Size::new(3840., 2160.), Edges::default());
+        assert_eq!(uhd.width, MAX_WIDTH);
+        assert_eq!(uhd.list_height, MAX_LIST_HEIGHT);
+
+        let qhd = Sizing::new(Size::new(2560., 1440.), Edges::default());
+        assert!(qhd.width > MIN_WIDTH && qhd.width < MAX_WIDTH);
+        assert_eq!(qhd.list_height, 650.);
+    }
+
+    #[test]
+    fn fits_small_outputs() {
+        let edges = Edges {
+            top: 32.,
+            bottom: 64.,
+            ..Edges::default()
+        };
+        let small = Sizing::new(Size::new(480., 360.), edges);
+
+        assert_eq!(small.width, 384.);
+        assert_eq!(small.list_height, 124.);
+    }
+}
@@ -0,0 +1,195 @@
+//! The `com.system76.CosmicLauncher1` D-Bus interface for scripting the launcher.
+//!
+//! Served on the session bus at `/com/system76/CosmicLauncher1`:
+//!
+//! - `Show(s query)`: open the launcher, searching for `query` if not empty.
+//! - `Hide()`: close the launcher if open.
+//! - `Toggle()`: open the launcher if closed, otherwise close it.
+//! - `SetQuery(s query)`: replace the search input.
+//! - `GetState() -> (b visible, s query, u results)`: the current state.
+//! - `TokenTimeouts` (`u`): property counting launches that went ahead without
+//!   an activation token because the compositor did not answer in time.
+//! - `Activated(s name, s description)`: signal emitted when a result is activated.
+
+use cosmic::iced::{Subscription, stream};
+use futures::SinkExt;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+use zbus::object_server::SignalEmitter;
+
+pub const NAME: &str = "com.system76.CosmicLauncher1";
+pub const PATH: &str = "/com/system76/CosmicLauncher1";
+
+/// Launcher state reported by `GetState`.
+#[derive(Debug, Clone, Default)]
+pub struct State {
+    pub visible: bool,
+    pub query: String,
+    pub results: u32,
+    pub token_timeouts: u32,
+}
+
+#[derive(Debug, Clone)]
+pub enum Request {
+    Activated { name: String, description: String },
+    TokenTimeoutsChanged,
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Started(Handle),
+    Show(String),
+    Hide,
+    Toggle,
+    SetQuery(String),
+}
+
+/// Lets the app publish its state and emit signals on the interface.
+#[derive(Debug, Clone)]
+pub struct Handle {
+    tx: mpsc::Sender<Request>,
+    state: Arc<Mutex<State>>,
+}
+
+impl Handle {
+    pub fn set_state(&self, state: State) {
+        let mut current = self.state.lock().unwrap();
+        let changed = current.token_timeouts != state.token_timeouts;
+        *current = state;
+        drop(current);
+
+        if changed && let Err(why) = self.tx.try_send(Request::TokenTimeoutsChanged) {
+            tracing::warn!("failed to queue TokenTimeouts change: {why}");
+        }
+    }
+
+    pub fn activated(&self, name: String, description: String) {
+        if let Err(why) = self.tx.try_send(Request::Activated { name, description }) {
+            tracing::warn!("failed to queue Activated signal: {why}");
+        }
+    }
+}
+
+struct Control {
+    output: futures::channel::mpsc::Sender<Event>,
+    state: Arc<Mutex<State>>,
+}
+
+impl Control {
+    async fn send(&mut self, event: Event) {
+        let _res = self.output.send(event).await;
+    }
+}
+
+#[zbus::interface(name = "com.system76.CosmicLauncher1")]
+impl Control {
+    async fn show(&mut self, query: String) {
+        self.send(Event::Show(query)).await;
+    }
+
+    async fn hide(&mut self) {
+        self.send(Event::Hide).await;
+    }
+
+    async fn toggle(&mut self) {
+        self.send(Event::Toggle).await;
+    }
+
+    async fn set_query(&mut self, query: String) {
+        self.send(Event::SetQuery(query)).await;
+    }
+
+    async fn get_state(&self) -> (bool, String, u32) {
+        let state = self.state.lock().unwrap();
+        (state.visible, state.query.clone(), state.results)
+    }
+
+    #[zbus(property)]
+    async fn token_timeouts(&self) -> u32 {
+        self.state.lock().unwrap().token_timeouts
+    }
+
+    #[zbus(signal)]
+    async fn activated(
+        emitter: &SignalEmitter<'_>,
+        name: &str,
+        description: &str,
+    ) -> zbus::Result<()>;
+}
+
+pub fn subscription<I: 'static + Hash + Copy + Send + Sync>(id: I) -> Subscription<Event> {
+    Subscription::run_with(id, |_| {
+        stream::channel(
+            4,
+            |mut output: futures::channel::mpsc::Sender<Event>| async move {
+                let state = Arc::new(Mutex::new(State::default()));
+                let control = Control {
+                    output: output.clone(),
+                    state: state.clone(),
+                };
+
+                let connection = match serve(control).await {
+                    Ok(connection) => connection,
+                    Err(why) => {
+                        tracing::error!("failed to serve {NAME}: {why}");
+                        return futures::future::pending().await;
+                    }
+                };
+
+                let (tx, mut rx) = mpsc::channel(8);
+                let _res = output.send(Event::Started(Handle { tx, state })).await;
+
+                while let Some(request) = rx.recv().await {
+                    match request {
+                        Request::Activated { name, description } => {
+                            if let Err(why) = emit_activated(&connection, &name, &description).await
+                            {
+                                tracing::error!("failed to emit Activated: {why}");
+                            }
+                        }
+                        Request::TokenTimeoutsChanged => {
+                            if let Err(why) = emit_token_timeouts_changed(&connection).await {
+                                tracing::error!("failed to emit TokenTimeouts change: {why}");
+                            }
+                        }
+                    }
+                }
+
+                futures::future::pending().await
+            },
+        )
+    })
+}
+
+async fn serve(control: Control) -> zbus::Result<zbus::Connection> {
+    zbus::connection::Builder::session()?
+        .name(NAME)?
+        .serve_at(PATH, control)?
+        .build()
+        .await
+}
+
+async fn emit_activated(
+    connection: &zbus::Connection,
+    name: &str,
+    description: &str,
+) -> zbus::Result<()> {
+    let iface = connection
+        .object_server()
+        .interface::<_, Control>(PATH)
+        .await?;
+    Control::activated(iface.signal_emitter(), name, description).await
+}
+
+async fn emit_token_timeouts_changed(connection: &zbus::Connection) -> zbus::Result<()> {
+    let iface = connection
+        .object_server()
+        .interface::<_, Control>(PATH)
+        .await?;
+    iface
+        .get()
+        .await
+        .token_timeouts_changed(iface.signal_emitter())
+        .await
+}
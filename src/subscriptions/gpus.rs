@@ -0,0 +1,309 @@
+//! GPUs reported by switcheroo-control, for launching apps on a chosen GPU.
+//!
+//! The list is fetched once and refreshed when switcheroo-control reports a
+//! property change or is restarted, so launching never waits on D-Bus.
+
+use cosmic::iced::{Subscription, stream};
+use futures::{SinkExt, Stream, StreamExt};
+use pop_launcher::GpuPreference;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::time::Duration;
+use switcheroo_control::SwitcherooControlProxy;
+
+const SERVICE: &str = "net.hadess.SwitcherooControl";
+const PATH: &str = "/net/hadess/SwitcherooControl";
+/// Time switcheroo-control has to answer before it is treated as unavailable.
+const TIMEOUT: Duration = Duration::from_millis(500);
+/// Time before connecting to the system bus again, doubled after each failure.
+const BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Gpu {
+    pub name: String,
+    /// Environment that makes a process render on this GPU.
+    pub environment: HashMap<String, String>,
+    pub default: bool,
+}
+
+/// Returns the environment that selects the preferred GPU.
+pub fn environment(gpus: &[Gpu], preference: GpuPreference) -> Option<HashMap<String, String>> {
+    match preference {
+        GpuPreference::Default => gpus.iter().find(|gpu| gpu.default),
+        GpuPreference::NonDefault => gpus.iter().find(|gpu| !gpu.default),
+        GpuPreference::SpecificIdx(idx) => gpus.get(idx as usize),
+    }
+    .map(|gpu| gpu.environment.clone())
+}
+
+/// Lists the GPUs of the switcheroo-control service on `connection`, in the
+/// order that `GpuPreference::SpecificIdx` indexes.
+pub async fn list(connection: &zbus::Connection) -> zbus::Result<Vec<Gpu>> {
+    let proxy = SwitcherooControlProxy::new(connection).await?;
+    let gpus = proxy.get_gpus().await?;
+    Ok(gpus
+        .into_iter()
+        .map(|gpu| Gpu {
+            name: gpu.name,
+            environment: gpu.environment,
+            default: gpu.default,
+        })
+        .collect())
+}
+
+/// Lists the GPUs of the system's switcheroo-control service.
+pub async fn system() -> zbus::Result<Vec<Gpu>> {
+    with_timeout(async {
+        let connection = zbus::Connection::system().await?;
+        list(&connection).await
+    })
+    .await
+}
+
+async fn with_timeout<T>(future: impl Future<Output = zbus::Result<T>>) -> zbus::Result<T> {
+    tokio::time::timeout(TIMEOUT, future)
+        .await
+        .unwrap_or_else(|_| Err(zbus::Error::Failure(format!("{SERVICE} timed out"))))
+}
+
+/// Emits the system's GPUs, and again whenever they change.
+pub fn subscription<I: 'static + Hash + Copy + Send + Sync>(id: I) -> Subscription<Vec<Gpu>> {
+    Subscription::run_with(id, |_| {
+        stream::channel(
+            1,
+            |mut output: futures::channel::mpsc::Sender<Vec<Gpu>>| async move {
+                let mut backoff = BACKOFF;
+                loop {
+                    let result = match with_timeout(zbus::Connection::system()).await {
+                        Ok(connection) => watch(&connection, &mut output).await,
+                        Err(why) => Err(why),
+                    };
+                    match result {
+                        Ok(()) => backoff = BACKOFF,
+                        Err(why) => tracing::debug!("{SERVICE} unavailable: {why}"),
+                    }
+
+                    tokio::time::sleep(backoff).await;
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                }
+            },
+        )
+    })
+}
+
+async fn watch(
+    connection: &zbus::Connection,
+    output: &mut futures::channel::mpsc::Sender<Vec<Gpu>>,
+) -> zbus::Result<()> {
+    let properties = zbus::fdo::PropertiesProxy::builder(connection)
+        .destination(SERVICE)?
+        .path(PATH)?
+        .build()
+        .await?;
+    // subscribe before listing, so that no change goes unnoticed
+    let changes = properties.receive_properties_changed().await?.map(|_| ());
+    // the service may start after the launcher, or be restarted
+    let owners = zbus::fdo::DBusProxy::new(connection)
+        .await?
+        .receive_name_owner_changed_with_args(&[(0, SERVICE)])
+        .await?
+        .map(|_| ());
+
+    refresh(connection, futures::stream::select(changes, owners), output).await;
+    Ok(())
+}
+
+/// Sends the GPUs, and again after each event, until the events end.
+///
+/// `HasDualGpu`, `NumGPUs` and `GPUs` change together, so any change is
+/// answered with a fresh list.
+async fn refresh(
+    connection: &zbus::Connection,
+    mut events: impl Stream<Item = ()> + Unpin,
+    output: &mut futures::channel::mpsc::Sender<Vec<Gpu>>,
+) {
+    loop {
+        match with_timeout(list(connection)).await {
+            Ok(gpus) => {
+                let _res = output.send(gpus).await;
+            }
+            Err(why) => tracing::debug!("failed to list GPUs: {why}"),
+        }
+        if events.next().await.is_none() {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+pub mod fake {
+    //! A switcheroo-control stand-in served on a private peer-to-peer bus.
+
+    use zbus::zvariant::{OwnedValue, Value};
+
+    struct FakeSwitcheroo {
+        gpus: Vec<(String, Vec<String>, bool)>,
+    }
+
+    #[zbus::interface(name = "net.hadess.SwitcherooControl")]
+    impl FakeSwitcheroo {
+        #[zbus(property, name = "GPUs")]
+        fn gpus(&self) -> Vec<std::collections::HashMap<String, OwnedValue>> {
+            self.gpus
+                .iter()
+                .map(|(name, environment, default)| {
+                    [
+                        ("Name", Value::from(name.clone())),
+                        ("Environment", Value::from(environment.clone())),
+                        ("Default", Value::from(*default)),
+                    ]
+                    .into_iter()
+                    .map(|(key, value)| (key.to_string(), value.try_into().unwrap()))
+                    .collect()
+                })
+                .collect()
+        }
+
+        #[zbus(property)]
+        fn has_dual_gpu(&self) -> bool {
+            self.gpus.len() > 1
+        }
+
+        #[zbus(property, name = "NumGPUs")]
+        fn num_gpus(&self) -> u32 {
+            u32::try_from(self.gpus.len()).unwrap()
+        }
+    }
+
+    /// Serves the given `(name, environment, default)` GPUs and returns a
+    /// connection to query them through, plus the serving end to keep alive.
+    ///
+    /// `environment` alternates keys and values, as switcheroo-control does.
+    pub async fn serve(
+        gpus: &[(&str, &[&str], bool)],
+    ) -> zbus::Result<(zbus::Connection, zbus::Connection)> {
+        let (server, client) = tokio::net::UnixStream::pair()?;
+        let fake = FakeSwitcheroo { gpus: owned(gpus) };
+
+        let server = zbus::connection::Builder::unix_stream(server)
+            .server(zbus::Guid::generate())?
+            .p2p()
+            .serve_at(super::PATH, fake)?
+            .build();
+        let client = zbus::connection::Builder::unix_stream(client).p2p().build();
+        let (server, client) = futures::try_join!(server, client)?;
+        Ok((client, server))
+    }
+
+    /// Replaces the GPUs served on the serving end returned by [`serve`].
+    pub async fn set(
+        server: &zbus::Connection,
+        gpus: &[(&str, &[&str], bool)],
+    ) -> zbus::Result<()> {
+        let fake = server
+            .object_server()
+            .interface::<_, FakeSwitcheroo>(super::PATH)
+            .await?;
+        fake.get_mut().await.gpus = owned(gpus);
+        Ok(())
+    }
+
+    fn owned(gpus: &[(&str, &[&str], bool)]) -> Vec<(String, Vec<String>, bool)> {
+        gpus.iter()
+            .map(|(name, environment, default)| {
+                let environment = environment.iter().map(|s| s.to_string()).collect();
+                (name.to_string(), environment, *default)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gpu(name: &str, default: bool) -> Gpu {
+        Gpu {
+            name: name.into(),
+            environment: HashMap::from([("GPU".into(), name.into())]),
+            default,
+        }
+    }
+
+    #[test]
+    fn environment_follows_preference() {
+        let gpus = [gpu("dgpu", false), gpu("igpu", true)];
+        let name = |preference| environment(&gpus, preference).map(|env| env["GPU"].clone());
+
+        assert_eq!(name(GpuPreference::Default).as_deref(), Some("igpu"));
+        assert_eq!(name(GpuPreference::NonDefault).as_deref(), Some("dgpu"));
+        assert_eq!(name(GpuPreference::SpecificIdx(1)).as_deref(), Some("igpu"));
+        assert_eq!(name(GpuPreference::SpecificIdx(2)), None);
+        assert_eq!(environment(&[], GpuPreference::Default), None);
+    }
+
+    #[tokio::test]
+    async fn lists_gpus_by_name() {
+        let (connection, _server) = fake::serve(&[
+            ("Intel Graphics", &["DRI_PRIME", "pci-0000_00_02_0"], true),
+            ("NVIDIA GeForce", &["__NV_PRIME_RENDER_OFFLOAD", "1"], false),
+        ])
+        .await
+        .unwrap();
+
+        let gpus = list(&connection).await.unwrap();
+
+        assert_eq!(
+            gpus,
+            [
+                Gpu {
+                    name: "Intel Graphics".into(),
+                    environment: HashMap::from([("DRI_PRIME".into(), "pci-0000_00_02_0".into())]),
+                    default: true,
+                },
+                Gpu {
+                    name: "NVIDIA GeForce".into(),
+                    environment: HashMap::from([("__NV_PRIME_RENDER_OFFLOAD".into(), "1".into())]),
+                    default: false,
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn refreshes_on_each_event() {
+        let (connection, server) = fake::serve(&[("Intel Graphics", &["DRI_PRIME", "0"], true)])
+            .await
+            .unwrap();
+        let (events, changes) = futures::channel::mpsc::unbounded();
+        let (output, mut received) = futures::channel::mpsc::channel(1);
+        let watching = tokio::spawn(async move {
+            let mut output = output;
+            refresh(&connection, changes, &mut output).await;
+        });
+        let names = |gpus: Option<Vec<Gpu>>| -> Vec<String> {
+            gpus.unwrap().into_iter().map(|gpu| gpu.name).collect()
+        };
+
+        assert_eq!(names(received.next().await), ["Intel Graphics"]);
+
+        fake::set(
+            &server,
+            &[
+                ("Intel Graphics", &["DRI_PRIME", "0"], true),
+                ("NVIDIA GeForce", &["__NV_PRIME_RENDER_OFFLOAD", "1"], false),
+            ],
+        )
+        .await
+        .unwrap();
+        events.unbounded_send(()).unwrap();
+        assert_eq!(
+            names(received.next().await),
+            ["Intel Graphics", "NVIDIA GeForce"]
+        );
+
+        drop(events);
+        watching.await.unwrap();
+    }
+}
@@ -1,10 +1,27 @@
 use cosmic::iced::futures::StreamExt;
 use cosmic::iced::runtime::futures::MaybeSend;
 use cosmic::iced::{Subscription, stream};
+use futures::future::BoxFuture;
+use futures::stream::BoxStream;
 use futures::{SinkExt, Stream};
 use pop_launcher_service::{Args, IpcClient};
 use std::hash::Hash;
+use std::io;
+use std::time::Duration;
 use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+
+#[cfg(test)]
+pub mod fake;
+
+/// Delay before the first restart attempt of a failed pop-launcher instance.
+const BACKOFF_INITIAL: Duration = Duration::from_millis(250);
+/// Upper bound for the delay between restart attempts.
+const BACKOFF_MAX: Duration = Duration::from_secs(8);
+/// Consecutive failures after which restarts stop until a retry is requested.
+const MAX_ATTEMPTS: u32 = 5;
+/// An instance that ran at least this long is considered to have been healthy.
+const STABLE_AFTER: Duration = Duration::from_secs(10);
 
 #[derive(Debug, Clone)]
 pub enum Request {
@@ -15,6 +32,7 @@ pub enum Request {
     ActivateContext(u32, u32),
     Close,
     ServiceIsClosed,
+    Retry,
 }
 
 #[derive(Debug, Clone)]
@@ -22,38 +40,178 @@ pub enum Event {
     Started(mpsc::Sender<Request>),
     Response(pop_launcher::Response),
     ServiceIsClosed,
+    State(ServiceState),
+}
+
+/// A running backend instance.
+pub trait Client: Send {
+    fn send(&mut self, request: pop_launcher::Request) -> BoxFuture<'_, io::Result<()>>;
+}
+
+/// A freshly started backend instance and its stream of responses.
+pub type Spawned = (Box<dyn Client>, BoxStream<'static, pop_launcher::Response>);
+
+/// Spawns backend instances on demand for [`service_with`].
+pub trait Backend: Send + 'static {
+    fn spawn(&mut self) -> io::Result<Spawned>;
+}
+
+/// The pop-launcher service, spawned as a child process.
+pub struct PopLauncher;
+
+impl Backend for PopLauncher {
+    fn spawn(&mut self) -> io::Result<Spawned> {
+        let (client, responses) = IpcClient::new_with_args(Args {
+            max_files: 20,
+            max_open: 99,
+            max_search: 20,
+        })?;
+        Ok((Box::new(client), responses.boxed()))
+    }
+}
+
+impl Client for IpcClient {
+    fn send(&mut self, request: pop_launcher::Request) -> BoxFuture<'_, io::Result<()>> {
+        Box::pin(IpcClient::send(self, request))
+    }
+}
+
+/// Connection state of the pop-launcher backend.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ServiceState {
+    /// No instance has been spawned yet.
+    Starting,
+    /// An instance is running and accepting requests.
+    Ready,
+    /// The instance failed to start or exited, and a restart is scheduled.
+    Crashed { attempt: u32 },
+    /// Too many consecutive failures; waiting for [`Request::Retry`].
+    GaveUp,
+}
+
+impl ServiceState {
+    pub fn is_unavailable(self) -> bool {
+        matches!(self, Self::Crashed { .. } | Self::GaveUp)
+    }
+}
+
+/// Exponential backoff for the given number of consecutive failures.
+fn backoff(failures: u32) -> Duration {
+    BACKOFF_INITIAL
+        .saturating_mul(1 << failures.saturating_sub(1).min(16))
+        .min(BACKOFF_MAX)
+}
+
+/// Tracks failures of the pop-launcher instance and decides when to restart it.
+struct Health {
+    state: ServiceState,
+    failures: u32,
+    retry_at: Option<Instant>,
+    started_at: Option<Instant>,
+}
+
+impl Health {
+    fn new() -> Self {
+        Self {
+            state: ServiceState::Starting,
+            failures: 0,
+            retry_at: None,
+            started_at: None,
+        }
+    }
+
+    fn can_start(&self) -> bool {
+        match self.state {
+            ServiceState::GaveUp => false,
+            _ => self.retry_at.is_none_or(|at| Instant::now() >= at),
+        }
+    }
+
+    fn started(&mut self) {
+        self.state = ServiceState::Ready;
+        self.retry_at = None;
+        self.started_at = Some(Instant::now());
+    }
+
+    fn failed(&mut self) {
+        if self
+            .started_at
+            .take()
+            .is_some_and(|at| at.elapsed() >= STABLE_AFTER)
+        {
+            self.failures = 0;
+        }
+
+        self.failures += 1;
+        if self.failures >= MAX_ATTEMPTS {
+            self.state = ServiceState::GaveUp;
+            self.retry_at = None;
+        } else {
+            self.state = ServiceState::Crashed {
+                attempt: self.failures,
+            };
+            self.retry_at = Some(Instant::now() + backoff(self.failures));
+        }
+    }
+
+    fn reset(&mut self) {
+        *self = Self::new();
+    }
 }
 
 pub fn subscription<I: 'static + Hash + Copy + Send + Sync>(
     id: I,
 ) -> cosmic::iced::Subscription<Event> {
-    Subscription::run_with(id, |_| {
-        stream::channel(
-            1,
-            |mut output: futures::channel::mpsc::Sender<Event>| async move {
-                loop {
-                    tracing::info!("starting pop-launcher service");
-                    let mut responses = std::pin::pin!(service());
-                    while let Some(message) = responses.next().await {
-                        let _res = output.send(message).await;
-                    }
+    Subscription::run_with(id, |_| restarting(service))
+}
+
+/// Forwards the events of a service, starting it again whenever it ends.
+///
+/// Restarts back off like those of a failed instance, starting over once the
+/// service ran for [`STABLE_AFTER`].
+pub fn restarting<S>(service: impl Fn() -> S + Send + 'static) -> impl Stream<Item = Event>
+where
+    S: Stream<Item = Event> + MaybeSend,
+{
+    stream::channel(
+        1,
+        move |mut output: futures::channel::mpsc::Sender<Event>| async move {
+            let mut restarts = 0;
+            loop {
+                tracing::info!("starting pop-launcher service");
+                let started_at = Instant::now();
+                let mut responses = std::pin::pin!(service());
+                while let Some(message) = responses.next().await {
+                    let _res = output.send(message).await;
+                }
+
+                if started_at.elapsed() >= STABLE_AFTER {
+                    restarts = 0;
                 }
-            },
-        )
-    })
+                restarts += 1;
+                let delay = backoff(restarts);
+                tracing::warn!(?delay, restarts, "pop-launcher service ended; restarting");
+                tokio::time::sleep(delay).await;
+            }
+        },
+    )
 }
 
-/// Initializes pop-launcher if it is not running, and returns a handle to its client.
-fn client_request<'a>(
+type ClientHandle = Option<(Box<dyn Client>, oneshot::Sender<()>)>;
+
+/// Initializes the backend if it is not running, and returns a handle to its client.
+///
+/// Restarts are rate limited by `health`, so this returns `None` while a
+/// backoff is pending or after giving up.
+async fn client_request<'a>(
     tx: &mpsc::Sender<Event>,
-    client: &'a mut Option<(IpcClient, oneshot::Sender<()>)>,
-) -> &'a mut Option<(IpcClient, oneshot::Sender<()>)> {
-    if client.is_none() {
-        *client = match pop_launcher_service::IpcClient::new_with_args(Args {
-            max_files: 20,
-            max_open: 99,
-            max_search: 20,
-        }) {
+    backend: &mut impl Backend,
+    client: &'a mut ClientHandle,
+    health: &mut Health,
+) -> &'a mut ClientHandle {
+    if client.is_none() && health.can_start() {
+        let _res = tx.send(Event::State(ServiceState::Starting)).await;
+        *client = match backend.spawn() {
             Ok((new_client, responses)) => {
                 let tx = tx.clone();
 
@@ -83,19 +241,31 @@ fn client_request<'a>(
                 #[cfg(not(feature = "console"))]
                 let _res = tokio::task::spawn(listener);
 
+                health.started();
                 Some((new_client, kill_tx))
             }
             Err(why) => {
-                tracing::error!("pop-launcher failed to start: {}", why);
+                health.failed();
+                tracing::error!(
+                    failures = health.failures,
+                    state = ?health.state,
+                    "pop-launcher failed to start: {}",
+                    why
+                );
                 None
             }
-        }
+        };
+        let _res = tx.send(Event::State(health.state)).await;
     }
 
     client
 }
 
 pub fn service() -> impl Stream<Item = Event> + MaybeSend {
+    service_with(PopLauncher)
+}
+
+pub fn service_with(mut backend: impl Backend) -> impl Stream<Item = Event> + MaybeSend {
     let (requests_tx, mut requests_rx) = mpsc::channel(4);
     let (responses_tx, responses_rx) = mpsc::channel(4);
 
@@ -103,43 +273,88 @@ pub fn service() -> impl Stream<Item = Event> + MaybeSend {
         let _res = responses_tx.send(Event::Started(requests_tx.clone())).await;
 
         let client = &mut None;
+        let mut health = Health::new();
+        let mut last_query = String::new();
+
+        loop {
+            // When a restart is pending, wake up once the backoff elapses and
+            // repeat the last search so the results come back on their own.
+            let request = match health.retry_at {
+                Some(deadline) => tokio::time::timeout_at(deadline, requests_rx.recv())
+                    .await
+                    .unwrap_or_else(|_| Some(Request::Search(last_query.clone()))),
+                None => requests_rx.recv().await,
+            };
+
+            let Some(request) = request else {
+                break;
+            };
 
-        while let Some(request) = requests_rx.recv().await {
             match request {
                 Request::Search(s) => {
-                    if let Some((client, _)) = client_request(&responses_tx, client) {
+                    last_query.clone_from(&s);
+                    if let Some((client, _)) =
+                        client_request(&responses_tx, &mut backend, client, &mut health).await
+                    {
                         let _res = client.send(pop_launcher::Request::Search(s)).await;
                     }
                 }
                 Request::Activate(i) => {
-                    if let Some((client, _)) = client_request(&responses_tx, client) {
+                    if let Some((client, _)) =
+                        client_request(&responses_tx, &mut backend, client, &mut health).await
+                    {
                         let _res = client.send(pop_launcher::Request::Activate(i)).await;
                     }
                 }
                 Request::Context(i) => {
-                    if let Some((client, _)) = client_request(&responses_tx, client) {
+                    if let Some((client, _)) =
+                        client_request(&responses_tx, &mut backend, client, &mut health).await
+                    {
                         let _res = client.send(pop_launcher::Request::Context(i)).await;
                     }
                 }
                 Request::ActivateContext(id, context) => {
-                    if let Some((client, _)) = client_request(&responses_tx, client) {
+                    if let Some((client, _)) =
+                        client_request(&responses_tx, &mut backend, client, &mut health).await
+                    {
                         let _res = client
                             .send(pop_launcher::Request::ActivateContext { id, context })
                             .await;
                     }
                 }
                 Request::Close => {
-                    if let Some((client, _)) = client_request(&responses_tx, client) {
+                    if let Some((client, _)) =
+                        client_request(&responses_tx, &mut backend, client, &mut health).await
+                    {
                         let _res = client.send(pop_launcher::Request::Close).await;
                     }
                 }
                 Request::Complete(id) => {
-                    if let Some((client, _)) = client_request(&responses_tx, client) {
+                    if let Some((client, _)) =
+                        client_request(&responses_tx, &mut backend, client, &mut health).await
+                    {
                         let _res = client.send(pop_launcher::Request::Complete(id)).await;
                     }
                 }
                 Request::ServiceIsClosed => {
                     *client = None;
+                    health.failed();
+                    tracing::warn!(
+                        failures = health.failures,
+                        state = ?health.state,
+                        "pop-launcher exited"
+                    );
+                    let _res = responses_tx.send(Event::State(health.state)).await;
+                }
+                Request::Retry => {
+                    health.reset();
+                    if let Some((client, _)) =
+                        client_request(&responses_tx, &mut backend, client, &mut health).await
+                    {
+                        let _res = client
+                            .send(pop_launcher::Request::Search(last_query.clone()))
+                            .await;
+                    }
                 }
             }
         }
@@ -155,3 +370,189 @@ pub fn service() -> impl Stream<Item = Event> + MaybeSend {
 
     tokio_stream::wrappers::ReceiverStream::new(responses_rx)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::fake::FakeBackend;
+    use super::*;
+    use crate::fixtures::result;
+    use pop_launcher::Response;
+
+    async fn next(events: &mut (impl Stream<Item = Event> + Unpin)) -> Event {
+        events.next().await.expect("service stream ended")
+    }
+
+    async fn started(events: &mut (impl Stream<Item = Event> + Unpin)) -> mpsc::Sender<Request> {
+        match next(events).await {
+            Event::Started(tx) => tx,
+            other => panic!("expected Started, got {other:?}"),
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn search_is_forwarded_to_backend() {
+        let fake = FakeBackend::new();
+        fake.reply([Response::Update(vec![result(0, "Firefox")])]);
+
+        let mut events = Box::pin(service_with(fake.clone()));
+        let tx = started(&mut events).await;
+        tx.send(Request::Search("fire".into())).await.unwrap();
+
+        assert!(matches!(
+            next(&mut events).await,
+            Event::State(ServiceState::Starting)
+        ));
+        assert!(matches!(
+            next(&mut events).await,
+            Event::State(ServiceState::Ready)
+        ));
+        assert!(matches!(
+            next(&mut events).await,
+            Event::Response(Response::Update(list)) if list[0].name == "Firefox"
+        ));
+        assert!(matches!(
+            &fake.requests()[..],
+            [pop_launcher::Request::Search(s)] if s == "fire"
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn failed_spawn_is_retried_after_backoff() {
+        let fake = FakeBackend::new();
+        fake.fail_spawns(1).reply([Response::Update(Vec::new())]);
+
+        let mut events = Box::pin(service_with(fake.clone()));
+        let tx = started(&mut events).await;
+        tx.send(Request::Search("term".into())).await.unwrap();
+
+        assert!(matches!(
+            next(&mut events).await,
+            Event::State(ServiceState::Starting)
+        ));
+        assert!(matches!(
+            next(&mut events).await,
+            Event::State(ServiceState::Crashed { attempt: 1 })
+        ));
+
+        // the paused clock advances through the backoff on its own
+        assert!(matches!(
+            next(&mut events).await,
+            Event::State(ServiceState::Starting)
+        ));
+        assert!(matches!(
+            next(&mut events).await,
+            Event::State(ServiceState::Ready)
+        ));
+        assert!(matches!(
+            next(&mut events).await,
+            Event::Response(Response::Update(_))
+        ));
+        assert_eq!(fake.spawns(), 1);
+        assert!(matches!(
+            &fake.requests()[..],
+            [pop_launcher::Request::Search(s)] if s == "term"
+        ));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn gives_up_until_retry() {
+        let fake = FakeBackend::new();
+        fake.fail_spawns(MAX_ATTEMPTS as usize);
+
+        let mut events = Box::pin(service_with(fake.clone()));
+        let tx = started(&mut events).await;
+        tx.send(Request::Search(String::new())).await.unwrap();
+
+        let mut crashes = 0;
+        loop {
+            match next(&mut events).await {
+                Event::State(ServiceState::Crashed { .. }) => crashes += 1,
+                Event::State(ServiceState::GaveUp) => break,
+                Event::State(ServiceState::Starting) => {}
+                other => panic!("unexpected event {other:?}"),
+            }
+        }
+        assert_eq!(crashes, MAX_ATTEMPTS - 1);
+        assert_eq!(fake.spawns(), 0);
+
+        tx.send(Request::Retry).await.unwrap();
+        assert!(matches!(
+            next(&mut events).await,
+            Event::State(ServiceState::Starting)
+        ));
+        assert!(matches!(
+            next(&mut events).await,
+            Event::State(ServiceState::Ready)
+        ));
+        assert_eq!(fake.spawns(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn exited_backend_is_restarted() {
+        let fake = FakeBackend::new();
+
+        let mut events = Box::pin(service_with(fake.clone()));
+        let tx = started(&mut events).await;
+        tx.send(Request::Search("a".into())).await.unwrap();
+        assert!(matches!(
+            next(&mut events).await,
+            Event::State(ServiceState::Starting)
+        ));
+        assert!(matches!(
+            next(&mut events).await,
+            Event::State(ServiceState::Ready)
+        ));
+
+        fake.crash();
+        assert!(matches!(next(&mut events).await, Event::ServiceIsClosed));
+        tx.send(Request::ServiceIsClosed).await.unwrap();
+        assert!(matches!(
+            next(&mut events).await,
+            Event::State(ServiceState::Crashed { attempt: 1 })
+        ));
+        assert!(matches!(
+            next(&mut events).await,
+            Event::State(ServiceState::Starting)
+        ));
+        assert!(matches!(
+            next(&mut events).await,
+            Event::State(ServiceState::Ready)
+        ));
+        assert_eq!(fake.spawns(), 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn restart_backoff_resets_after_a_healthy_run() {
+        // each run of the service lasts as long as the next duration, then ends
+        let runs = std::sync::Mutex::new(std::collections::VecDeque::from([
+            Duration::ZERO,
+            Duration::ZERO,
+            STABLE_AFTER,
+            Duration::ZERO,
+        ]));
+        let mut events = Box::pin(restarting(move || {
+            let run = runs.lock().unwrap().pop_front().unwrap_or(Duration::MAX);
+            futures::stream::once(async move {
+                tokio::time::sleep(run).await;
+                Event::ServiceIsClosed
+            })
+        }));
+
+        let mut ended = Vec::new();
+        for _ in 0..4 {
+            next(&mut events).await;
+            ended.push(Instant::now());
+        }
+        let gaps: Vec<_> = ended.windows(2).map(|w| w[1] - w[0]).collect();
+
+        assert_eq!(
+            gaps,
+            [
+                backoff(1),
+                backoff(2) + STABLE_AFTER,
+                // not `backoff(3)`, since the previous run was healthy
+                backoff(1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! A scriptable stand-in for pop-launcher, for tests that run without the
+//! real service installed.
+
+use super::{Backend, Client, Spawned};
+use futures::StreamExt;
+use futures::future::BoxFuture;
+use pop_launcher::{Request, Response};
+use std::collections::VecDeque;
+use std::io;
+use std::sync::{Arc, Mutex};
+use tokio::sync::mpsc;
+
+#[derive(Default)]
+struct Script {
+    replies: VecDeque<Vec<Response>>,
+    requests: Vec<Request>,
+    spawns: usize,
+    failing_spawns: usize,
+    responses: Option<mpsc::UnboundedSender<Response>>,
+}
+
+/// Replays canned responses, one batch per request received.
+///
+/// Clones share the same script, so a test keeps a handle to inspect what the
+/// service sent after handing a clone to [`super::service_with`].
+#[derive(Clone, Default)]
+pub struct FakeBackend {
+    script: Arc<Mutex<Script>>,
+}
+
+impl FakeBackend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues the responses to emit for the next request without a reply.
+    pub fn reply(&self, responses: impl IntoIterator<Item = Response>) -> &Self {
+        self.script
+            .lock()
+            .unwrap()
+            .replies
+            .push_back(responses.into_iter().collect());
+        self
+    }
+
+    /// Makes the next `count` spawn attempts fail.
+    pub fn fail_spawns(&self, count: usize) -> &Self {
+        self.script.lock().unwrap().failing_spawns = count;
+        self
+    }
+
+    /// Ends the response stream of the running instance, as if it had exited.
+    pub fn crash(&self) {
+        self.script.lock().unwrap().responses = None;
+    }
+
+    /// All requests received so far, across instances.
+    pub fn requests(&self) -> Vec<Request> {
+        self.script.lock().unwrap().requests.clone()
+    }
+
+    /// Number of instances that were successfully spawned.
+    pub fn spawns(&self) -> usize {
+        self.script.lock().unwrap().spawns
+    }
+}
+
+impl Backend for FakeBackend {
+    fn spawn(&mut self) -> io::Result<Spawned> {
+        let mut script = self.script.lock().unwrap();
+        if script.failing_spawns > 0 {
+            script.failing_spawns -= 1;
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "fake spawn failure",
+            ));
+        }
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        script.spawns += 1;
+        script.responses = Some(tx);
+
+        let client = FakeClient {
+            script: self.script.clone(),
+        };
+        let responses = tokio_stream::wrappers::UnboundedReceiverStream::new(rx).boxed();
+        Ok((Box::new(client), responses))
+    }
+}
+
+struct FakeClient {
+    script: Arc<Mutex<Script>>,
+}
+
+impl Client for FakeClient {
+    fn send(&mut self, request: Request) -> BoxFuture<'_, io::Result<()>> {
+        let mut script = self.script.lock().unwrap();
+        script.requests.push(request);
+
+        let replies = script.replies.pop_front().unwrap_or_default();
+        let result = match &script.responses {
+            Some(tx) => {
+                for response in replies {
+                    let _res = tx.send(response);
+                }
+                Ok(())
+            }
+            None => Err(io::Error::from(io::ErrorKind::BrokenPipe)),
+        };
+
+        Box::pin(async move { result })
+    }
+}
@@ -1 +1,6 @@
+pub mod dbus;
+pub mod gpus;
 pub mod launcher;
+pub mod providers;
+pub mod scripts;
+pub mod wayland;
@@ -0,0 +1,184 @@
+//! In-process result providers that are merged with the pop-launcher results.
+//!
+//! Providers answer with the same [`pop_launcher::Response`] values as the
+//! backend, so the app handles their results through the same code paths.
+
+mod actions;
+
+use crate::ids;
+use crate::subscriptions::launcher::Request;
+use pop_launcher::{ContextOption, Response, SearchResult};
+
+/// Bits of a result id reserved for the provider's own id, below the index
+/// of the provider.
+const LOCAL_ID_BITS: u32 = 20;
+const LOCAL_ID_MASK: u32 = (1 << LOCAL_ID_BITS) - 1;
+
+/// A source of search results that runs inside the launcher.
+///
+/// Methods are called on the UI thread, so they must return quickly.
+pub trait Provider {
+    /// Returns the results for `query`. Ids only need to be unique per provider.
+    fn query(&mut self, query: &str) -> Vec<SearchResult>;
+
+    /// Activates the result with the given id.
+    fn activate(&mut self, id: u32) -> Option<Response>;
+
+    /// Returns the context options of the result with the given id.
+    fn context(&mut self, _id: u32) -> Vec<ContextOption> {
+        Vec::new()
+    }
+
+    /// Activates a context option of the result with the given id.
+    fn activate_context(&mut self, _id: u32, _context: u32) -> Option<Response> {
+        None
+    }
+
+    /// Returns the text to fill the search input with for the given id.
+    fn complete(&mut self, _id: u32) -> Option<String> {
+        None
+    }
+
+    /// Returns results to list under a result of another source.
+    fn children(&mut self, _parent: &SearchResult) -> Vec<SearchResult> {
+        Vec::new()
+    }
+}
+
+/// Returns `true` if the result id was assigned by [`Providers::query`].
+pub fn owns(id: u32) -> bool {
+    id & ids::PROVIDER != 0
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn encode(provider: usize, id: u32) -> u32 {
+    ids::PROVIDER | ((provider as u32) << LOCAL_ID_BITS) | (id & LOCAL_ID_MASK)
+}
+
+fn decode(id: u32) -> (usize, u32) {
+    (
+        ((id & ids::LOCAL) >> LOCAL_ID_BITS) as usize,
+        id & LOCAL_ID_MASK,
+    )
+}
+
+/// The registered providers.
+#[derive(Default)]
+pub struct Providers {
+    providers: Vec<Box<dyn Provider>>,
+}
+
+impl Providers {
+    /// Creates the set of built-in providers.
+    pub fn new() -> Self {
+        Self {
+            providers: vec![Box::new(actions::Actions::new())],
+        }
+    }
+
+    /// Queries every provider, rewriting result ids so they can be routed back.
+    pub fn query(&mut self, query: &str) -> Vec<SearchResult> {
+        self.providers
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(i, provider)| {
+                provider.query(query).into_iter().map(move |mut result| {
+                    result.id = encode(i, result.id);
+                    result
+                })
+            })
+            .collect()
+    }
+
+    /// Returns the results that every provider lists under `parent`.
+    pub fn children(&mut self, parent: &SearchResult) -> Vec<SearchResult> {
+        self.providers
+            .iter_mut()
+            .enumerate()
+            .flat_map(|(i, provider)| {
+                provider
+                    .children(parent)
+                    .into_iter()
+                    .map(move |mut result| {
+                        result.id = encode(i, result.id);
+                        result
+                    })
+            })
+            .collect()
+    }
+
+    /// Handles a request for a provider result, returning the provider's response.
+    pub fn handle(&mut self, request: &Request) -> Option<Response> {
+        match *request {
+            Request::Activate(id) => {
+                let (i, local) = decode(id);
+                self.providers.get_mut(i)?.activate(local)
+            }
+            Request::Context(id) => {
+                let (i, local) = decode(id);
+                let options = self.providers.get_mut(i)?.context(local);
+                Some(Response::Context { id, options })
+            }
+            Request::ActivateContext(id, context) => {
+                let (i, local) = decode(id);
+                self.providers.get_mut(i)?.activate_context(local, context)
+            }
+            Request::Complete(id) => {
+                let (i, local) = decode(id);
+                self.providers
+                    .get_mut(i)?
+                    .complete(local)
+                    .map(Response::Fill)
+            }
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fallbacks;
+    use crate::fixtures::result;
+    use crate::subscriptions::scripts;
+
+    /// Lists a result per id, and fills the input with the id it activates.
+    struct Fixed(Vec<u32>);
+
+    impl Provider for Fixed {
+        fn query(&mut self, _query: &str) -> Vec<SearchResult> {
+            self.0.iter().map(|&id| result(id, "fixed")).collect()
+        }
+
+        fn activate(&mut self, id: u32) -> Option<Response> {
+            Some(Response::Fill(id.to_string()))
+        }
+    }
+
+    #[test]
+    fn ids_round_trip() {
+        for (provider, id) in [(0, 0), (1, 42), (3, LOCAL_ID_MASK)] {
+            let encoded = encode(provider, id);
+            assert!(owns(encoded));
+            assert!(!scripts::owns(encoded));
+            assert!(!fallbacks::owns(encoded));
+            assert_eq!(decode(encoded), (provider, id));
+        }
+        assert!(!owns(7));
+    }
+
+    #[test]
+    fn routes_requests_to_the_listing_provider() {
+        let mut providers = Providers {
+            providers: vec![Box::new(Fixed(vec![1])), Box::new(Fixed(vec![1, 2]))],
+        };
+
+        let ids: Vec<_> = providers.query("").iter().map(|item| item.id).collect();
+        assert_eq!(ids, [encode(0, 1), encode(1, 1), encode(1, 2)]);
+        assert!(matches!(
+            providers.handle(&Request::Activate(ids[2])),
+            Some(Response::Fill(id)) if id == "2"
+        ));
+        assert!(providers.handle(&Request::Activate(encode(2, 1))).is_none());
+    }
+}
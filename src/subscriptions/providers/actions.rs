@@ -0,0 +1,271 @@
+//! Desktop actions, such as "New Private Window", as results of their own.
+//!
+//! The actions of an app are listed as sub-results under it, and can also be
+//! found by their name. Desktop entries are loaded on a separate thread, and
+//! reloaded when the launcher is opened after a while.
+
+use super::Provider;
+use cosmic::desktop::fde;
+use pop_launcher::{GpuPreference, IconSource, Response, SearchResult};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// Bits of a local id that hold the index of the action within its app, so
+/// only the first 64 actions of an app are listed.
+const ACTION_BITS: u32 = 6;
+const ACTION_MASK: u32 = (1 << ACTION_BITS) - 1;
+/// Maximum number of actions found by name.
+const MAX_RESULTS: usize = 5;
+/// Time after which desktop entries are reloaded when the launcher opens.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(60);
+
+/// An app with desktop actions.
+#[derive(Debug, Clone)]
+pub struct App {
+    pub name: String,
+    pub icon: Option<IconSource>,
+    pub path: PathBuf,
+    /// Localized names of the actions, in the order of the desktop entry.
+    pub actions: Vec<String>,
+}
+
+pub struct Actions {
+    apps: Vec<App>,
+    loading: Option<mpsc::Receiver<Vec<App>>>,
+    loaded_at: Option<Instant>,
+}
+
+impl Actions {
+    /// Starts loading the installed apps.
+    pub fn new() -> Self {
+        let mut actions = Self::with_apps(Vec::new());
+        actions.reload();
+        actions
+    }
+
+    pub fn with_apps(apps: Vec<App>) -> Self {
+        Self {
+            apps,
+            loading: None,
+            loaded_at: Some(Instant::now()),
+        }
+    }
+
+    fn reload(&mut self) {
+        let (tx, rx) = mpsc::channel();
+        let spawned = std::thread::Builder::new()
+            .name("desktop-actions".into())
+            .spawn(move || {
+                let _res = tx.send(load());
+            });
+        match spawned {
+            Ok(_) => self.loading = Some(rx),
+            Err(why) => tracing::error!("failed to load desktop actions: {why}"),
+        }
+    }
+
+    /// Picks up the apps once they are loaded.
+    fn poll(&mut self) {
+        let Some(rx) = &self.loading else {
+            return;
+        };
+        match rx.try_recv() {
+            Ok(apps) => {
+                self.apps = apps;
+                self.loading = None;
+                self.loaded_at = Some(Instant::now());
+            }
+            Err(mpsc::TryRecvError::Empty) => {}
+            Err(mpsc::TryRecvError::Disconnected) => self.loading = None,
+        }
+    }
+
+    fn result(&self, app: usize, action: usize) -> Option<SearchResult> {
+        let entry = self.apps.get(app)?;
+        // a larger index would spill into the bits of the app
+        let index = u32::try_from(action).ok().filter(|&i| i <= ACTION_MASK)?;
+        #[allow(clippy::cast_possible_truncation)]
+        let id = ((app as u32) << ACTION_BITS) | index;
+        Some(SearchResult {
+            id,
+            name: entry.actions.get(action)?.clone(),
+            description: entry.name.clone(),
+            icon: entry.icon.clone(),
+            category_icon: None,
+            window: None,
+        })
+    }
+}
+
+fn load() -> Vec<App> {
+    let locales = fde::get_languages_from_env();
+    cosmic::desktop::load_applications(&locales, false, None)
+        .into_iter()
+        .filter(|entry| !entry.desktop_actions.is_empty())
+        .filter_map(|entry| {
+            let icon = match entry.icon {
+                cosmic::desktop::IconSource::Name(name) => name,
+                cosmic::desktop::IconSource::Path(path) => path.to_string_lossy().into_owned(),
+            };
+            Some(App {
+                name: entry.name,
+                icon: Some(IconSource::Name(icon.into())),
+                path: entry.path?,
+                actions: entry
+                    .desktop_actions
+                    .into_iter()
+                    .map(|action| action.name)
+                    .collect(),
+            })
+        })
+        .collect()
+}
+
+fn words(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split_whitespace().map(str::to_lowercase)
+}
+
+impl Provider for Actions {
+    /// Finds actions whose name, or the name of their app, has a word starting
+    /// with each word of the query, as long as one of them is in the action.
+    fn query(&mut self, query: &str) -> Vec<SearchResult> {
+        self.poll();
+        let query: Vec<_> = words(query).collect();
+        if query.is_empty() {
+            let stale = self
+                .loaded_at
+                .is_none_or(|loaded_at| loaded_at.elapsed() > RELOAD_INTERVAL);
+            if stale && self.loading.is_none() {
+                self.reload();
+            }
+            return Vec::new();
+        }
+
+        let mut results = Vec::new();
+        for (i, app) in self.apps.iter().enumerate() {
+            let app_words: Vec<_> = words(&app.name).collect();
+            for (j, action) in app.actions.iter().enumerate() {
+                let action_words: Vec<_> = words(action).collect();
+                let in_action = |word: &String| action_words.iter().any(|w| w.starts_with(word));
+                let matches = query.iter().any(in_action)
+                    && query.iter().all(|word| {
+                        in_action(word) || app_words.iter().any(|w| w.starts_with(word))
+                    });
+                if matches {
+                    results.extend(self.result(i, j));
+                }
+                if results.len() == MAX_RESULTS {
+                    return results;
+                }
+            }
+        }
+        results
+    }
+
+    fn activate(&mut self, id: u32) -> Option<Response> {
+        let (app, action) = ((id >> ACTION_BITS) as usize, (id & ACTION_MASK) as usize);
+        let app = self.apps.get(app)?;
+        // the action is looked up by its unlocalized name when it is launched
+        let action_name = cosmic::desktop::load_desktop_file(&[], app.path.clone())
+            .and_then(|entry| entry.desktop_actions.into_iter().nth(action))
+            .map_or_else(
+                || app.actions.get(action).cloned(),
+                |action| Some(action.name),
+            )?;
+        Some(Response::DesktopEntry {
+            path: app.path.clone(),
+            gpu_preference: GpuPreference::Default,
+            action_name: Some(action_name),
+        })
+    }
+
+    fn children(&mut self, parent: &SearchResult) -> Vec<SearchResult> {
+        self.poll();
+        let Some(i) = self.apps.iter().position(|app| app.name == parent.name) else {
+            return Vec::new();
+        };
+        (0..self.apps[i].actions.len())
+            .filter_map(|j| self.result(i, j))
+            .map(|mut result| {
+                // the app's icon as shown by pop-launcher
+                result.icon.clone_from(&parent.icon);
+                result
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::result;
+
+    fn actions() -> Actions {
+        Actions::with_apps(vec![
+            App {
+                name: "Firefox".into(),
+                icon: None,
+                path: "/nonexistent/firefox.desktop".into(),
+                actions: vec!["New Window".into(), "New Private Window".into()],
+            },
+            App {
+                name: "Thunderbird".into(),
+                icon: None,
+                path: "/nonexistent/thunderbird.desktop".into(),
+                actions: vec!["Compose Message".into()],
+            },
+        ])
+    }
+
+    fn names(results: Vec<SearchResult>) -> Vec<String> {
+        results.into_iter().map(|result| result.name).collect()
+    }
+
+    #[test]
+    fn finds_actions_by_name() {
+        let mut actions = actions();
+
+        assert_eq!(names(actions.query("private")), ["New Private Window"]);
+        assert_eq!(
+            names(actions.query("new")),
+            ["New Window", "New Private Window"]
+        );
+        assert_eq!(names(actions.query("fire priv")), ["New Private Window"]);
+        assert_eq!(names(actions.query("compose")), ["Compose Message"]);
+        assert!(actions.query("firefox").is_empty());
+        assert!(actions.query("").is_empty());
+    }
+
+    #[test]
+    fn lists_actions_under_their_app() {
+        let mut actions = actions();
+        let parent = SearchResult {
+            icon: Some(IconSource::Name("thunderbird".into())),
+            ..result(7, "Thunderbird")
+        };
+
+        let children = actions.children(&parent);
+
+        assert_eq!(children.len(), 1);
+        assert!(matches!(
+            &children[0].icon,
+            Some(IconSource::Name(name)) if name == "thunderbird"
+        ));
+        assert!(matches!(
+            actions.activate(children[0].id),
+            Some(Response::DesktopEntry { action_name: Some(name), .. }) if name == "Compose Message"
+        ));
+    }
+
+    #[test]
+    fn caps_actions_per_app() {
+        let mut actions = actions();
+        actions.apps[0].actions = (0..70).map(|i| format!("Action {i}")).collect();
+        let firefox = actions.children(&result(0, "Firefox"));
+        let thunderbird = actions.children(&result(0, "Thunderbird"));
+
+        assert_eq!(firefox.len(), 64);
+        assert!(firefox.iter().all(|action| action.id != thunderbird[0].id));
+    }
+}
@@ -0,0 +1,224 @@
+//! User scripts that add results for queries starting with their trigger.
+//!
+//! Executables in `~/.config/cosmic-launcher/sources/` are triggered by their
+//! file name up to the first `.` followed by a space: `gh.sh` handles `gh …`.
+//!
+//! A query runs `<script> query <text>`, which prints one JSON record per line:
+//!
+//! ```json
+//! {"name": "Issue #12", "description": "Fix crash", "icon": "github", "action": "12"}
+//! ```
+//!
+//! Only `name` is required. Activating a result runs `<script> activate <action>`,
+//! where `action` defaults to the name.
+
+use crate::ids;
+use pop_launcher::{IconSource, SearchResult};
+use serde::Deserialize;
+use std::os::unix::fs::PermissionsExt;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::process::Command;
+use tokio::time::Instant;
+
+/// Time a script has to print its results before it is killed.
+const QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+/// Maximum number of results read from a single script.
+const MAX_RESULTS: usize = 50;
+
+/// Returns `true` if the result id was assigned to a script result.
+pub fn owns(id: u32) -> bool {
+    id & ids::SCRIPT != 0
+}
+
+#[derive(Debug, Clone)]
+struct Source {
+    trigger: String,
+    path: PathBuf,
+}
+
+/// A result printed by a script, with what to send back when it is activated.
+#[derive(Debug, Clone)]
+pub struct Match {
+    pub result: SearchResult,
+    path: PathBuf,
+    action: String,
+}
+
+#[derive(Deserialize)]
+struct Record {
+    name: String,
+    #[serde(default)]
+    description: String,
+    icon: Option<String>,
+    action: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Scripts {
+    sources: Vec<Source>,
+    /// Results for the current query.
+    pub matches: Vec<Match>,
+}
+
+impl Scripts {
+    fn dir() -> Option<PathBuf> {
+        let config = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+        Some(config.join("cosmic-launcher").join("sources"))
+    }
+
+    /// Discovers the executables in the sources directory.
+    pub fn scan(&mut self) {
+        let Some(entries) = Self::dir().and_then(|dir| std::fs::read_dir(dir).ok()) else {
+            self.sources.clear();
+            return;
+        };
+
+        self.sources = entries
+            .filter_map(Result::ok)
+            .filter(|entry| {
+                entry
+                    .metadata()
+                    .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+            })
+            .filter_map(|entry| {
+                let name = entry.file_name().into_string().ok()?;
+                let trigger = name.split('.').next().filter(|t| !t.is_empty())?;
+                Some(Source {
+                    trigger: trigger.to_string(),
+                    path: entry.path(),
+                })
+            })
+            .collect();
+    }
+
+    /// Returns a future that queries every script triggered by `query`.
+    pub fn search(&self, query: &str) -> Option<impl Future<Output = Vec<Match>> + Send + 'static> {
+        let queries: Vec<_> = self
+            .sources
+            .iter()
+            .enumerate()
+            .filter_map(|(i, source)| {
+                let rest = query.strip_prefix(&source.trigger)?.strip_prefix(' ')?;
+                Some(run_query(i, source.clone(), rest.to_string()))
+            })
+            .collect();
+
+        if queries.is_empty() {
+            return None;
+        }
+
+        Some(async move {
+            futures::future::join_all(queries)
+                .await
+                .into_iter()
+                .flatten()
+                .collect()
+        })
+    }
+
+    /// Returns the trigger of the script that handles `query`, if any.
+    pub fn trigger(&self, query: &str) -> Option<&str> {
+        self.sources
+            .iter()
+            .map(|source| source.trigger.as_str())
+            .find(|trigger| {
+                query
+                    .strip_prefix(trigger)
+                    .is_some_and(|rest| rest.starts_with(' '))
+            })
+    }
+
+    pub fn get(&self, id: u32) -> Option<&Match> {
+        self.matches.iter().find(|m| m.result.id == id)
+    }
+}
+
+async fn run_query(index: usize, source: Source, query: String) -> Vec<Match> {
+    let child = Command::new(&source.path)
+        .arg("query")
+        .arg(&query)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn();
+
+    let mut child = match child {
+        Ok(child) => child,
+        Err(why) => {
+            tracing::error!(path = %source.path.display(), "failed to run script source: {why}");
+            return Vec::new();
+        }
+    };
+
+    let Some(stdout) = child.stdout.take() else {
+        return Vec::new();
+    };
+
+    let deadline = Instant::now() + QUERY_TIMEOUT;
+    let mut lines = BufReader::new(stdout).lines();
+    let mut matches = Vec::new();
+    while matches.len() < MAX_RESULTS {
+        let line = match tokio::time::timeout_at(deadline, lines.next_line()).await {
+            Ok(Ok(Some(line))) => line,
+            Ok(Ok(None)) => break,
+            Ok(Err(why)) => {
+                tracing::warn!(path = %source.path.display(), "failed to read script output: {why}");
+                break;
+            }
+            Err(_) => {
+                tracing::warn!(path = %source.path.display(), "script source timed out");
+                break;
+            }
+        };
+
+        let record: Record = match serde_json::from_str(&line) {
+            Ok(record) => record,
+            Err(why) => {
+                tracing::debug!(path = %source.path.display(), "invalid script record: {why}");
+                continue;
+            }
+        };
+
+        #[allow(clippy::cast_possible_truncation)]
+        let id = ids::SCRIPT | ((index as u32) << 16) | matches.len() as u32;
+        matches.push(Match {
+            result: SearchResult {
+                id,
+                name: record.name.clone(),
+                description: record.description,
+                icon: record.icon.map(|icon| IconSource::Name(icon.into())),
+                category_icon: None,
+                window: None,
+            },
+            path: source.path.clone(),
+            action: record.action.unwrap_or(record.name),
+        });
+    }
+
+    matches
+}
+
+/// Runs the script that produced `m` to activate it.
+pub async fn activate(m: Match) {
+    let status = Command::new(&m.path)
+        .arg("activate")
+        .arg(&m.action)
+        .stdin(Stdio::null())
+        .status()
+        .await;
+
+    match status {
+        Ok(status) if !status.success() => {
+            tracing::warn!(path = %m.path.display(), %status, "script activation failed");
+        }
+        Ok(_) => {}
+        Err(why) => {
+            tracing::error!(path = %m.path.display(), "failed to run script source: {why}");
+        }
+    }
+}
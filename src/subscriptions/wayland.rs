@@ -0,0 +1,417 @@
+//! Places launched apps on a chosen workspace or output.
+//!
+//! Activation tokens carry no placement, so the window of a launched app is
+//! moved once it appears, through the workspace and toplevel management
+//! protocols. A separate Wayland connection runs on its own thread, because
+//! the protocols are not exposed by the app's connection.
+
+use cosmic::cctk::cosmic_protocols::toplevel_management::v1::client::zcosmic_toplevel_manager_v1;
+use cosmic::cctk::sctk::output::{OutputHandler, OutputState};
+use cosmic::cctk::sctk::reexports::calloop::{self, channel};
+use cosmic::cctk::sctk::reexports::calloop_wayland_source::WaylandSource;
+use cosmic::cctk::sctk::registry::{ProvidesRegistryState, RegistryState};
+use cosmic::cctk::toplevel_info::{ToplevelInfoHandler, ToplevelInfoState};
+use cosmic::cctk::toplevel_management::{ToplevelManagerHandler, ToplevelManagerState};
+use cosmic::cctk::wayland_client::globals::registry_queue_init;
+use cosmic::cctk::wayland_client::protocol::wl_output::WlOutput;
+use cosmic::cctk::wayland_client::{Connection, QueueHandle, WEnum};
+use cosmic::cctk::wayland_protocols::ext::foreign_toplevel_list::v1::client::ext_foreign_toplevel_handle_v1::ExtForeignToplevelHandleV1;
+use cosmic::cctk::wayland_protocols::ext::workspace::v1::client::ext_workspace_handle_v1::{
+    self, ExtWorkspaceHandleV1,
+};
+use cosmic::cctk::workspace::{WorkspaceHandler, WorkspaceState};
+use cosmic::cctk::{self, sctk};
+use cosmic::iced::{Subscription, stream};
+use futures::SinkExt;
+use std::hash::Hash;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// Time a launched app has to open a window before it is no longer moved.
+const PLACEMENT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where to put the window of a launched app.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Placement {
+    /// The workspace at this index on the output the window opens on.
+    Workspace(usize),
+    /// The empty workspace COSMIC keeps after the others.
+    NewWorkspace,
+    /// The active workspace of the output with this name.
+    Output(String),
+}
+
+/// Workspaces and outputs to offer as placements.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Layout {
+    /// Number of workspaces on the output with the most of them.
+    pub workspaces: usize,
+    pub outputs: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    Started(Handle),
+    Layout(Layout),
+}
+
+#[derive(Debug)]
+enum Request {
+    Place {
+        app_id: String,
+        placement: Placement,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct Handle {
+    tx: channel::Sender<Request>,
+}
+
+impl Handle {
+    /// Moves the next window opened by `app_id` according to `placement`.
+    pub fn place(&self, app_id: String, placement: Placement) {
+        if let Err(why) = self.tx.send(Request::Place { app_id, placement }) {
+            tracing::warn!("failed to queue window placement: {why}");
+        }
+    }
+}
+
+pub fn subscription<I: 'static + Hash + Copy + Send + Sync>(id: I) -> Subscription<Event> {
+    Subscription::run_with(id, |_| {
+        stream::channel(
+            4,
+            |mut output: futures::channel::mpsc::Sender<Event>| async move {
+                let (tx, rx) = channel::channel();
+                let (events_tx, mut events_rx) = mpsc::unbounded_channel();
+                let spawned = std::thread::Builder::new()
+                    .name("wayland-placement".into())
+                    .spawn(move || {
+                        if let Err(why) = run(rx, events_tx) {
+                            tracing::warn!("window placement unavailable: {why}");
+                        }
+                    });
+                if let Err(why) = spawned {
+                    tracing::error!("failed to start window placement: {why}");
+                    return futures::future::pending().await;
+                }
+
+                let _res = output.send(Event::Started(Handle { tx })).await;
+                while let Some(event) = events_rx.recv().await {
+                    let _res = output.send(event).await;
+                }
+
+                futures::future::pending().await
+            },
+        )
+    })
+}
+
+struct Pending {
+    app_id: String,
+    placement: Placement,
+    deadline: Instant,
+}
+
+struct State {
+    registry_state: RegistryState,
+    output_state: OutputState,
+    workspace_state: WorkspaceState,
+    toplevel_info_state: ToplevelInfoState,
+    toplevel_manager_state: ToplevelManagerState,
+    pending: Vec<Pending>,
+    /// Windows opened while placements were pending that did not match one,
+    /// as their app id may only be set by a later update.
+    unplaced: Vec<ExtForeignToplevelHandleV1>,
+    layout: Layout,
+    events: mpsc::UnboundedSender<Event>,
+    exit: bool,
+}
+
+fn run(
+    requests: channel::Channel<Request>,
+    events: mpsc::UnboundedSender<Event>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let connection = Connection::connect_to_env()?;
+    let (globals, event_queue) = registry_queue_init(&connection)?;
+    let qh = event_queue.handle();
+
+    let mut event_loop = calloop::EventLoop::<State>::try_new()?;
+    WaylandSource::new(connection, event_queue).insert(event_loop.handle())?;
+    event_loop
+        .handle()
+        .insert_source(requests, |event, _, state| match event {
+            channel::Event::Msg(Request::Place { app_id, placement }) => {
+                state.pending.push(Pending {
+                    app_id,
+                    placement,
+                    deadline: Instant::now() + PLACEMENT_TIMEOUT,
+                });
+            }
+            channel::Event::Closed => state.exit = true,
+        })?;
+
+    let registry_state = RegistryState::new(&globals);
+    let mut state = State {
+        output_state: OutputState::new(&globals, &qh),
+        workspace_state: WorkspaceState::new(&registry_state, &qh),
+        toplevel_info_state: ToplevelInfoState::try_new(&registry_state, &qh)
+            .ok_or("toplevel info protocol unsupported")?,
+        toplevel_manager_state: ToplevelManagerState::try_new(&registry_state, &qh)
+            .ok_or("toplevel management protocol unsupported")?,
+        registry_state,
+        pending: Vec::new(),
+        unplaced: Vec::new(),
+        layout: Layout::default(),
+        events,
+        exit: false,
+    };
+
+    while !state.exit {
+        event_loop.dispatch(None, &mut state)?;
+    }
+    Ok(())
+}
+
+impl State {
+    fn output_name(&self, output: &WlOutput) -> Option<String> {
+        self.output_state.info(output)?.name
+    }
+
+    fn update_layout(&mut self) {
+        let layout = Layout {
+            workspaces: self
+                .workspace_state
+                .workspace_groups()
+                .map(|group| group.workspaces.len())
+                .max()
+                .unwrap_or_default(),
+            outputs: self
+                .output_state
+                .outputs()
+                .filter_map(|output| self.output_name(&output))
+                .collect(),
+        };
+
+        if layout != self.layout {
+            self.layout = layout.clone();
+            let _res = self.events.send(Event::Layout(layout));
+        }
+    }
+
+    /// Finds the workspace to move `toplevel`, which opened on `output`, to,
+    /// and the output of that workspace.
+    fn target(
+        &self,
+        toplevel: &ExtForeignToplevelHandleV1,
+        output: Option<&WlOutput>,
+        placement: &Placement,
+    ) -> Option<(ExtWorkspaceHandleV1, WlOutput)> {
+        let mut groups = self.workspace_state.workspace_groups();
+        let group = match placement {
+            Placement::Output(name) => groups.find(|group| {
+                group
+                    .outputs
+                    .iter()
+                    .any(|output| self.output_name(output).as_ref() == Some(name))
+            })?,
+            _ => {
+                let groups: Vec<_> = groups.collect();
+                groups
+                    .iter()
+                    .find(|group| output.is_some_and(|output| group.outputs.contains(output)))
+                    .or_else(|| groups.first())
+                    .copied()?
+            }
+        };
+
+        let mut workspaces: Vec<_> = group
+            .workspaces
+            .iter()
+            .filter_map(|handle| self.workspace_state.workspace_info(handle))
+            .collect();
+        workspaces.sort_by(|a, b| a.coordinates.cmp(&b.coordinates));
+
+        let workspace = match placement {
+            Placement::Workspace(index) => workspaces.get(*index)?,
+            Placement::NewWorkspace => {
+                let occupied: Vec<_> = workspaces
+                    .iter()
+                    .map(|workspace| {
+                        self.toplevel_info_state.toplevels().any(|info| {
+                            info.foreign_toplevel != *toplevel
+                                && info.workspace.contains(&workspace.handle)
+                        })
+                    })
+                    .collect();
+                workspaces.get(new_workspace(&occupied)?)?
+            }
+            Placement::Output(_) => workspaces.iter().find(|workspace| {
+                workspace
+                    .state
+                    .contains(ext_workspace_handle_v1::State::Active)
+            })?,
+        };
+
+        Some((workspace.handle.clone(), group.outputs.first()?.clone()))
+    }
+
+    /// Moves a new window if its app was launched with a placement that has
+    /// not expired.
+    fn place(&mut self, toplevel: &ExtForeignToplevelHandleV1) {
+        let now = Instant::now();
+        self.pending.retain(|pending| pending.deadline > now);
+        if self.pending.is_empty() {
+            self.unplaced.clear();
+            return;
+        }
+
+        let Some(info) = self.toplevel_info_state.info(toplevel) else {
+            return;
+        };
+        let Some(i) = self.pending.iter().position(|pending| {
+            let app_id = pending.app_id.trim_end_matches(".desktop");
+            app_id.eq_ignore_ascii_case(&info.app_id)
+        }) else {
+            return;
+        };
+        let Some(cosmic_toplevel) = info.cosmic_toplevel.clone() else {
+            return;
+        };
+
+        let pending = self.pending.remove(i);
+        self.unplaced.retain(|unplaced| unplaced != toplevel);
+        let Some((workspace, output)) =
+            self.target(toplevel, info.output.iter().next(), &pending.placement)
+        else {
+            tracing::warn!(app_id = %pending.app_id, placement = ?pending.placement, "no workspace to place window on");
+            return;
+        };
+        self.toplevel_manager_state.manager.move_to_ext_workspace(
+            &cosmic_toplevel,
+            &workspace,
+            &output,
+        );
+    }
+}
+
+/// Returns the index of the empty workspace after the last one with windows,
+/// given whether each workspace has windows.
+///
+/// With a fixed number of workspaces the last one may have windows, in which
+/// case there is no new workspace.
+fn new_workspace(occupied: &[bool]) -> Option<usize> {
+    let index = occupied
+        .iter()
+        .rposition(|&occupied| occupied)
+        .map_or(0, |i| i + 1);
+    (index < occupied.len()).then_some(index)
+}
+
+impl ProvidesRegistryState for State {
+    fn registry(&mut self) -> &mut RegistryState {
+        &mut self.registry_state
+    }
+
+    sctk::registry_handlers!(OutputState);
+}
+
+impl OutputHandler for State {
+    fn output_state(&mut self) -> &mut OutputState {
+        &mut self.output_state
+    }
+
+    fn new_output(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _output: WlOutput) {
+        self.update_layout();
+    }
+
+    fn update_output(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _output: WlOutput) {
+        self.update_layout();
+    }
+
+    fn output_destroyed(&mut self, _conn: &Connection, _qh: &QueueHandle<Self>, _output: WlOutput) {
+        self.update_layout();
+    }
+}
+
+impl WorkspaceHandler for State {
+    fn workspace_state(&mut self) -> &mut WorkspaceState {
+        &mut self.workspace_state
+    }
+
+    fn done(&mut self) {
+        self.update_layout();
+    }
+}
+
+impl ToplevelInfoHandler for State {
+    fn toplevel_info_state(&mut self) -> &mut ToplevelInfoState {
+        &mut self.toplevel_info_state
+    }
+
+    fn new_toplevel(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        toplevel: &ExtForeignToplevelHandleV1,
+    ) {
+        self.unplaced.push(toplevel.clone());
+        self.place(toplevel);
+    }
+
+    fn update_toplevel(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        toplevel: &ExtForeignToplevelHandleV1,
+    ) {
+        if self.unplaced.contains(toplevel) {
+            self.place(toplevel);
+        }
+    }
+
+    fn toplevel_closed(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        toplevel: &ExtForeignToplevelHandleV1,
+    ) {
+        self.unplaced.retain(|unplaced| unplaced != toplevel);
+    }
+}
+
+impl ToplevelManagerHandler for State {
+    fn toplevel_manager_state(&mut self) -> &mut ToplevelManagerState {
+        &mut self.toplevel_manager_state
+    }
+
+    fn capabilities(
+        &mut self,
+        _conn: &Connection,
+        _qh: &QueueHandle<Self>,
+        _capabilities: Vec<
+            WEnum<zcosmic_toplevel_manager_v1::ZcosmicToplelevelManagementCapabilitiesV1>,
+        >,
+    ) {
+    }
+}
+
+sctk::delegate_output!(State);
+sctk::delegate_registry!(State);
+cctk::delegate_workspace!(State);
+cctk::delegate_toplevel_info!(State);
+cctk::delegate_toplevel_manager!(State);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_workspace_follows_occupied_ones() {
+        assert_eq!(new_workspace(&[true, false]), Some(1));
+        assert_eq!(new_workspace(&[true, false, true, false, false]), Some(3));
+        assert_eq!(new_workspace(&[false, false]), Some(0));
+        assert_eq!(new_workspace(&[false, true]), None);
+        assert_eq!(new_workspace(&[]), None);
+    }
+}
@@ -0,0 +1,143 @@
+//! The terminal emulator that `Terminal=true` apps and `t:` commands run in.
+//!
+//! The configured `terminal` is a command line template that the command to
+//! run is appended to, such as `foot`, `kitty --` or `cosmic-term -e`. If its
+//! program is missing, the first installed fallback is used instead.
+
+use crate::app::{exec_program, program_exists};
+use crate::open_with::push_quoted;
+use std::fmt::Display;
+
+/// Templates tried in order when the configured one is unusable.
+const FALLBACKS: &[&str] = &[
+    "cosmic-term -e",
+    "gnome-terminal --",
+    "konsole -e",
+    "kitty --",
+    "alacritty -e",
+    "foot",
+    "xterm -e",
+];
+
+/// Shells that a template may already run the command with, as `sh -c`.
+const SHELLS: &[&str] = &["sh", "bash", "dash", "zsh", "fish"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TemplateError {
+    Empty,
+    /// The template has an unterminated quote.
+    Unquoted,
+    NotFound(String),
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Empty => write!(f, "the template is empty"),
+            Self::Unquoted => write!(f, "the template has an unterminated quote"),
+            Self::NotFound(program) => write!(f, "{program}: command not found"),
+        }
+    }
+}
+
+/// Checks that a template names a program that is installed.
+pub fn validate(template: &str) -> Result<(), TemplateError> {
+    if template.trim().is_empty() {
+        return Err(TemplateError::Empty);
+    }
+    let program = exec_program(template).ok_or(TemplateError::Unquoted)?;
+    if !program_exists(&program) {
+        return Err(TemplateError::NotFound(program));
+    }
+    Ok(())
+}
+
+/// Returns the configured template if it is usable, otherwise the first
+/// installed fallback.
+pub fn resolve(configured: &str) -> Option<&str> {
+    match validate(configured) {
+        Ok(()) => return Some(configured),
+        Err(why) => tracing::warn!(
+            template = configured,
+            "unusable terminal, falling back: {why}"
+        ),
+    }
+    FALLBACKS
+        .iter()
+        .copied()
+        .find(|template| validate(template).is_ok())
+}
+
+/// Wraps `exec` in the configured terminal if it needs one, returning the
+/// command line and whether it still needs a terminal.
+///
+/// Without a configured or installed terminal, the choice is left to
+/// `spawn_desktop_exec`.
+pub fn command(configured: Option<&str>, exec: String, terminal: bool) -> (String, bool) {
+    match configured.filter(|_| terminal).and_then(resolve) {
+        Some(template) => (format!("{template} {exec}"), false),
+        None => (exec, terminal),
+    }
+}
+
+/// Returns the command line that runs a shell command in a terminal.
+///
+/// The command is run with `sh -c`, unless the template already ends with a
+/// shell's `-c`.
+pub fn shell(template: &str, command: &str) -> String {
+    let words: Vec<_> = template.split_whitespace().collect();
+    let runs_shell = matches!(
+        words.as_slice(),
+        [.., shell, "-c"] if SHELLS.contains(&shell.rsplit('/').next().unwrap_or(shell))
+    );
+    let mut exec = if runs_shell {
+        format!("{template} ")
+    } else {
+        format!("{template} sh -c ")
+    };
+    push_quoted(&mut exec, &[command]);
+    exec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_templates() {
+        assert_eq!(validate("  "), Err(TemplateError::Empty));
+        assert_eq!(validate(r#""sh -c"#), Err(TemplateError::Unquoted));
+        assert_eq!(
+            validate("/nonexistent/term -e"),
+            Err(TemplateError::NotFound("/nonexistent/term".into()))
+        );
+        assert_eq!(validate("sh -c"), Ok(()));
+    }
+
+    #[test]
+    fn wraps_commands() {
+        assert_eq!(
+            command(Some("sh -c"), "htop".into(), true),
+            ("sh -c htop".into(), false)
+        );
+        assert_eq!(
+            command(Some("sh -c"), "htop".into(), false),
+            ("htop".into(), false)
+        );
+        assert_eq!(command(None, "htop".into(), true), ("htop".into(), true));
+        assert_eq!(
+            shell("foot", r#"echo "$HOME""#),
+            r#"foot sh -c "echo \"\$HOME\"""#
+        );
+        assert_eq!(shell("sh -c", "htop"), r#"sh -c "htop""#);
+        // field codes are read from the line again when it is spawned
+        assert_eq!(
+            shell("sh -c", "date +%F && printf 100%%"),
+            r#"sh -c "date +%%F && printf 100%%%%""#
+        );
+        assert_eq!(
+            shell("kitty -- /bin/bash -c", "htop"),
+            r#"kitty -- /bin/bash -c "htop""#
+        );
+    }
+}